@@ -0,0 +1,101 @@
+#ifndef ENIGMA_H
+#define ENIGMA_H
+
+/* Generated by cbindgen from src/lib.rs, do not edit by hand */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Result of every fallible call
+typedef enum EnigmaStatus {
+  ENIGMA_STATUS_OK = 0,
+  // A required pointer argument was NULL
+  ENIGMA_STATUS_NULL_POINTER = 1,
+  // The settings string could not be parsed
+  ENIGMA_STATUS_INVALID_SETTINGS = 2,
+  // The input contained a character outside of `A`-`Z`
+  ENIGMA_STATUS_INVALID_LETTER = 3,
+} EnigmaStatus;
+
+// Opaque machine handle, created by `enigma_new` and released by `enigma_free`
+typedef struct EnigmaMachine EnigmaMachine;
+
+// Saved machine state, see `enigma_snapshot` and `enigma_restore`
+typedef struct EnigmaSnapshot {
+  // Rotor positions left to right, `0` is `A`
+  uint8_t positions[3];
+} EnigmaSnapshot;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create a machine from a settings string.
+//
+// On success `*out` holds a new machine that must be released with
+// `enigma_free`. On failure `*out` is left untouched.
+//
+// # Safety
+//
+// `settings` must be NULL or a valid NUL-terminated string and `out` must be
+// NULL or valid for writes.
+enum EnigmaStatus enigma_new(const char *settings, struct EnigmaMachine **out);
+
+// Release a machine. Passing NULL is a no-op.
+//
+// # Safety
+//
+// `machine` must be NULL or a pointer returned by `enigma_new` that has not
+// been freed yet.
+void enigma_free(struct EnigmaMachine *machine);
+
+// Encrypt (or decrypt) `len` uppercase letters from `input` into `output`,
+// stepping the rotors as the machine would. `input` and `output` may be the
+// same buffer. If any character is not in `A`-`Z` nothing is written and the
+// machine does not move.
+//
+// # Safety
+//
+// `machine` must come from `enigma_new`, `input` must be valid for `len`
+// reads and `output` for `len` writes.
+enum EnigmaStatus enigma_encrypt(struct EnigmaMachine *machine,
+                                 const char *input,
+                                 size_t len,
+                                 char *output);
+
+// Write the three window letters, left to right, to `out`.
+//
+// # Safety
+//
+// `machine` must come from `enigma_new` and `out` must be valid for 3 writes.
+enum EnigmaStatus enigma_get_positions(const struct EnigmaMachine *machine, char *out);
+
+// Turn the rotors to the three window letters in `positions`, left to right.
+//
+// # Safety
+//
+// `machine` must come from `enigma_new` and `positions` must be valid for 3
+// reads.
+enum EnigmaStatus enigma_set_positions(struct EnigmaMachine *machine, const char *positions);
+
+// Save the current machine state to `out`.
+//
+// # Safety
+//
+// `machine` must come from `enigma_new` and `out` must be valid for writes.
+enum EnigmaStatus enigma_snapshot(const struct EnigmaMachine *machine, struct EnigmaSnapshot *out);
+
+// Restore a state saved by `enigma_snapshot`.
+//
+// # Safety
+//
+// `machine` must come from `enigma_new` and `snapshot` must be valid for
+// reads.
+enum EnigmaStatus enigma_restore(struct EnigmaMachine *machine,
+                                 const struct EnigmaSnapshot *snapshot);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ENIGMA_H */
@@ -0,0 +1,260 @@
+//! # C ABI
+//!
+//! A stable C interface around [`Enigma`], built as a `cdylib`. The matching
+//! header `include/enigma.h` is generated by cbindgen and checked by the
+//! tests, run them with `ENIGMA_UPDATE_HEADER=1` to regenerate it.
+//!
+//! Every function reports failures through [`EnigmaStatus`] and validates its
+//! input before touching the machine, so no Rust panic is ever raised across
+//! the boundary.
+//!
+//! Machines are M3s created from a settings string in the layout of a
+//! [`DailyKey`]: reflector, the three rotors left to right, the three ring
+//! settings, any number of plug pairs and optionally the start positions, e.g.
+//! `"B II IV V 02 21 12 AV BS CG DL FU HZ IN KM OW RX BLA"`.
+
+use std::ffi::c_char;
+use std::ffi::CStr;
+
+use enigma::core::alphabet::EnigmaAlphabet;
+use enigma::core::engine::Enigma;
+use enigma::historical_machines::model::Model;
+use enigma::key::DailyKey;
+
+/// Result of every fallible call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnigmaStatus {
+    Ok = 0,
+    /// A required pointer argument was NULL
+    NullPointer = 1,
+    /// The settings string could not be parsed
+    InvalidSettings = 2,
+    /// The input contained a character outside of `A`-`Z`
+    InvalidLetter = 3,
+}
+
+/// Opaque machine handle, created by `enigma_new` and released by `enigma_free`
+pub struct EnigmaMachine {
+    enigma: Enigma,
+}
+
+/// Saved machine state, see `enigma_snapshot` and `enigma_restore`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnigmaSnapshot {
+    /// Rotor positions left to right, `0` is `A`
+    pub positions: [u8; 3],
+}
+
+/// Create a machine from a settings string.
+///
+/// On success `*out` holds a new machine that must be released with
+/// `enigma_free`. On failure `*out` is left untouched.
+///
+/// # Safety
+///
+/// `settings` must be NULL or a valid NUL-terminated string and `out` must be
+/// NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_new(
+    settings: *const c_char,
+    out: *mut *mut EnigmaMachine,
+) -> EnigmaStatus {
+    if settings.is_null() || out.is_null() {
+        return EnigmaStatus::NullPointer;
+    }
+    let Some(enigma) = CStr::from_ptr(settings).to_str().ok().and_then(parse_settings) else {
+        return EnigmaStatus::InvalidSettings;
+    };
+    *out = Box::into_raw(Box::new(EnigmaMachine { enigma }));
+    EnigmaStatus::Ok
+}
+
+/// Release a machine. Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `machine` must be NULL or a pointer returned by `enigma_new` that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_free(machine: *mut EnigmaMachine) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+/// Encrypt (or decrypt) `len` uppercase letters from `input` into `output`,
+/// stepping the rotors as the machine would. `input` and `output` may be the
+/// same buffer. If any character is not in `A`-`Z` nothing is written and the
+/// machine does not move.
+///
+/// # Safety
+///
+/// `machine` must come from `enigma_new`, `input` must be valid for `len`
+/// reads and `output` for `len` writes.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_encrypt(
+    machine: *mut EnigmaMachine,
+    input: *const c_char,
+    len: usize,
+    output: *mut c_char,
+) -> EnigmaStatus {
+    if machine.is_null() || input.is_null() || output.is_null() {
+        return EnigmaStatus::NullPointer;
+    }
+    if (0..len).any(|i| !(*input.add(i) as u8).is_ascii_uppercase()) {
+        return EnigmaStatus::InvalidLetter;
+    }
+    let enigma = &mut (*machine).enigma;
+    for i in 0..len {
+        let letter = EnigmaAlphabet::from_ascii(*input.add(i) as u8);
+        *output.add(i) = enigma.encode(letter).to_char() as c_char;
+    }
+    EnigmaStatus::Ok
+}
+
+/// Write the three window letters, left to right, to `out`.
+///
+/// # Safety
+///
+/// `machine` must come from `enigma_new` and `out` must be valid for 3 writes.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_get_positions(
+    machine: *const EnigmaMachine,
+    out: *mut c_char,
+) -> EnigmaStatus {
+    if machine.is_null() || out.is_null() {
+        return EnigmaStatus::NullPointer;
+    }
+    for (i, position) in (*machine).enigma.positions().into_iter().enumerate() {
+        *out.add(i) = position.to_char() as c_char;
+    }
+    EnigmaStatus::Ok
+}
+
+/// Turn the rotors to the three window letters in `positions`, left to right.
+///
+/// # Safety
+///
+/// `machine` must come from `enigma_new` and `positions` must be valid for 3
+/// reads.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_set_positions(
+    machine: *mut EnigmaMachine,
+    positions: *const c_char,
+) -> EnigmaStatus {
+    if machine.is_null() || positions.is_null() {
+        return EnigmaStatus::NullPointer;
+    }
+    let positions = [*positions as u8, *positions.add(1) as u8, *positions.add(2) as u8];
+    if !positions.iter().all(u8::is_ascii_uppercase) {
+        return EnigmaStatus::InvalidLetter;
+    }
+    (*machine).enigma.set_positions(positions.map(EnigmaAlphabet::from_ascii));
+    EnigmaStatus::Ok
+}
+
+/// Save the current machine state to `out`.
+///
+/// # Safety
+///
+/// `machine` must come from `enigma_new` and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_snapshot(
+    machine: *const EnigmaMachine,
+    out: *mut EnigmaSnapshot,
+) -> EnigmaStatus {
+    if machine.is_null() || out.is_null() {
+        return EnigmaStatus::NullPointer;
+    }
+    *out = EnigmaSnapshot { positions: (*machine).enigma.positions().map(u8::from) };
+    EnigmaStatus::Ok
+}
+
+/// Restore a state saved by `enigma_snapshot`.
+///
+/// # Safety
+///
+/// `machine` must come from `enigma_new` and `snapshot` must be valid for
+/// reads.
+#[no_mangle]
+pub unsafe extern "C" fn enigma_restore(
+    machine: *mut EnigmaMachine,
+    snapshot: *const EnigmaSnapshot,
+) -> EnigmaStatus {
+    if machine.is_null() || snapshot.is_null() {
+        return EnigmaStatus::NullPointer;
+    }
+    let positions = (*snapshot).positions;
+    if positions.iter().any(|&p| p >= 26) {
+        return EnigmaStatus::InvalidLetter;
+    }
+    (*machine).enigma.set_positions(positions.map(EnigmaAlphabet::new));
+    EnigmaStatus::Ok
+}
+
+fn parse_settings(settings: &str) -> Option<Enigma> {
+    let key = settings.parse::<DailyKey>().ok()?;
+    Enigma::from_key(Model::M3, &key).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    const BARBAROSSA: &std::ffi::CStr = c"B II IV V 02 21 12 AV BS CG DL FU HZ IN KM OW RX BLA";
+
+    #[test]
+    fn test_ffi_round_trip() {
+        let mut machine = ptr::null_mut();
+        unsafe {
+            assert_eq!(enigma_new(BARBAROSSA.as_ptr(), &mut machine), EnigmaStatus::Ok);
+
+            let mut snapshot = EnigmaSnapshot { positions: [0; 3] };
+            assert_eq!(enigma_snapshot(machine, &mut snapshot), EnigmaStatus::Ok);
+
+            let mut buffer = *b"EDPUDNRGYS";
+            let buffer = buffer.as_mut_ptr().cast::<c_char>();
+            assert_eq!(enigma_encrypt(machine, buffer, 10, buffer), EnigmaStatus::Ok);
+            assert_eq!(std::slice::from_raw_parts(buffer.cast::<u8>(), 10), b"AUFKLXABTE");
+
+            assert_eq!(enigma_restore(machine, &snapshot), EnigmaStatus::Ok);
+            let mut positions = [0 as c_char; 3];
+            assert_eq!(enigma_get_positions(machine, positions.as_mut_ptr()), EnigmaStatus::Ok);
+            assert_eq!(positions.map(|p| p as u8), *b"BLA");
+
+            enigma_free(machine);
+        }
+    }
+
+    #[test]
+    fn test_ffi_errors() {
+        let mut machine = ptr::null_mut();
+        unsafe {
+            assert_eq!(enigma_new(ptr::null(), &mut machine), EnigmaStatus::NullPointer);
+            for settings in [c"B II IV", c"B II IV IX 01 01 01", c"B I II III 01 01 01 AB BC"] {
+                assert_eq!(
+                    enigma_new(settings.as_ptr(), &mut machine),
+                    EnigmaStatus::InvalidSettings
+                );
+            }
+            assert!(machine.is_null());
+
+            assert_eq!(enigma_new(BARBAROSSA.as_ptr(), &mut machine), EnigmaStatus::Ok);
+            let input = c"AB-C";
+            let mut output = [0 as c_char; 4];
+            assert_eq!(
+                enigma_encrypt(machine, input.as_ptr(), 4, output.as_mut_ptr()),
+                EnigmaStatus::InvalidLetter
+            );
+            let mut positions = [0 as c_char; 3];
+            enigma_get_positions(machine, positions.as_mut_ptr());
+            assert_eq!(positions.map(|p| p as u8), *b"BLA");
+
+            enigma_free(machine);
+        }
+    }
+}
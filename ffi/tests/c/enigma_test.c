@@ -0,0 +1,55 @@
+/* Drives the C API end to end, see tests/c_api.rs */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "enigma.h"
+
+#define CHECK(cond)                                                  \
+  do {                                                               \
+    if (!(cond)) {                                                   \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+              #cond);                                                \
+      return 1;                                                      \
+    }                                                                \
+  } while (0)
+
+int main(void) {
+  const char *settings = "B II IV V 02 21 12 AV BS CG DL FU HZ IN KM OW RX";
+  const char *ciphertext = "EDPUDNRGYSZRCXNUYTPOMRMBOFKTBZ";
+  const char *plaintext = "AUFKLXABTEILUNGXVONXKURTINOWAX";
+  size_t len = strlen(ciphertext);
+  char buffer[64];
+  char positions[3];
+  EnigmaMachine *machine = NULL;
+  EnigmaSnapshot snapshot;
+
+  CHECK(enigma_new("B II IV", &machine) == ENIGMA_STATUS_INVALID_SETTINGS);
+  CHECK(machine == NULL);
+  CHECK(enigma_new(settings, &machine) == ENIGMA_STATUS_OK);
+
+  /* message key "KCH" enciphered at the indicator setting "WXC" */
+  CHECK(enigma_set_positions(machine, "WXC") == ENIGMA_STATUS_OK);
+  CHECK(enigma_encrypt(machine, "KCH", 3, buffer) == ENIGMA_STATUS_OK);
+  CHECK(memcmp(buffer, "BLA", 3) == 0);
+
+  CHECK(enigma_set_positions(machine, buffer) == ENIGMA_STATUS_OK);
+  CHECK(enigma_snapshot(machine, &snapshot) == ENIGMA_STATUS_OK);
+  CHECK(enigma_encrypt(machine, ciphertext, len, buffer) == ENIGMA_STATUS_OK);
+  CHECK(memcmp(buffer, plaintext, len) == 0);
+
+  /* back to the message key and decrypt again, this time in place */
+  CHECK(enigma_restore(machine, &snapshot) == ENIGMA_STATUS_OK);
+  CHECK(enigma_get_positions(machine, positions) == ENIGMA_STATUS_OK);
+  CHECK(memcmp(positions, "BLA", 3) == 0);
+  memcpy(buffer, ciphertext, len);
+  CHECK(enigma_encrypt(machine, buffer, len, buffer) == ENIGMA_STATUS_OK);
+  CHECK(memcmp(buffer, plaintext, len) == 0);
+
+  CHECK(enigma_encrypt(machine, "abc", 3, buffer) == ENIGMA_STATUS_INVALID_LETTER);
+  CHECK(enigma_encrypt(NULL, "ABC", 3, buffer) == ENIGMA_STATUS_NULL_POINTER);
+
+  enigma_free(machine);
+  enigma_free(NULL);
+  return 0;
+}
@@ -0,0 +1,38 @@
+//! Builds the `cdylib`, compiles `tests/c/enigma_test.c` against it and runs
+//! the resulting program.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn c_program_drives_the_library() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // target/<profile>/deps/<this test> -> target/<profile>
+    let target_dir = env::current_exe().unwrap().parent().unwrap().parent().unwrap().to_path_buf();
+    let program = target_dir.join("enigma_c_test");
+
+    // `cargo test` does not build `cdylib` targets on its own
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo.args(["build", "--lib", "-p", env!("CARGO_PKG_NAME")]);
+    if !cfg!(debug_assertions) {
+        cargo.arg("--release");
+    }
+    assert!(cargo.status().unwrap().success(), "failed to build the library");
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".into()))
+        .arg(manifest_dir.join("tests/c/enigma_test.c"))
+        .args(["-std=c99", "-Wall", "-Werror", "-I"])
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(&target_dir)
+        .arg(format!("-Wl,-rpath,{}", target_dir.display()))
+        .args(["-lenigma", "-o"])
+        .arg(&program)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "failed to compile the C test program");
+
+    let status = Command::new(&program).status().unwrap();
+    assert!(status.success(), "C test program failed");
+}
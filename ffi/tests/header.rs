@@ -0,0 +1,30 @@
+//! Checks that `include/enigma.h` matches what cbindgen generates from
+//! `src/lib.rs`, rewriting it instead when `ENIGMA_UPDATE_HEADER` is set.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn header_is_up_to_date() {
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(crate_dir.join("src/lib.rs"))
+        .generate()
+        .expect("unable to generate C header")
+        .write(&mut generated);
+
+    let header = crate_dir.join("include/enigma.h");
+    if env::var_os("ENIGMA_UPDATE_HEADER").is_some() {
+        fs::write(&header, &generated).unwrap();
+        return;
+    }
+    let committed = fs::read(&header).unwrap();
+    assert!(
+        committed == generated,
+        "include/enigma.h is out of date, rerun with ENIGMA_UPDATE_HEADER=1"
+    );
+}
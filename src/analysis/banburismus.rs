@@ -0,0 +1,435 @@
+//! Banburismus
+//!
+//! Turing's attack on naval Enigma. Two messages whose keys differ only in the
+//! right rotor letter are in depth once one is moved along by the distance
+//! between those letters: from there on both are enciphered at the same
+//! positions. Two German texts laid over each other in depth repeat a letter
+//! about once in 17, against once in 26 for random text, and the repeats come
+//! in runs where words recur.
+//!
+//! [`overlay`] slides one ciphertext along the other and weighs the repeats at
+//! every offset in decibans, a tenth of the base 10 logarithm of the odds. The
+//! best offset gives the distance between the two right rotor letters. The
+//! Scritchmus, [`scritchmus`], puts such distances together into chains of
+//! letters in alphabet order. A depth also means the middle rotor did not move
+//! between the two letters, which tells where the notch of the right rotor
+//! cannot be and with it which rotors can stand on the right.
+//!
+//! Once the right rotor and its window letters are known, messages whose keys
+//! agree only in the left letter give away the middle rotor the same way. In
+//! depth the left rotor did not move, and the best offset counts the turnovers
+//! of the right rotor in between, the steps of the middle rotor, see
+//! [`Distance::from_middle_overlap`]. The Scritchmus of those distances chains
+//! the middle letters and tells where the notch of the middle rotor cannot be.
+
+use core::fmt;
+use core::ops::Range;
+
+use super::letters;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::historical_machines::model::RotorId;
+
+/// Decibans given to each letter pair of an overlay
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weights {
+    /// For a repeated letter
+    pub repeat: f64,
+    /// For two different letters, a little below zero
+    pub mismatch: f64,
+    /// Extra for a run of repeats at least this many letters long, the
+    /// longest entry that fits counting
+    pub runs: Vec<(usize, f64)>,
+}
+
+impl Weights {
+    /// Weights for repeats once in `1 / depth` letters in depth and once in
+    /// `1 / random` out of it, without extra for runs
+    pub fn from_rates(depth: f64, random: f64) -> Self {
+        Self {
+            repeat: 10.0 * (depth / random).log10(),
+            mismatch: 10.0 * ((1.0 - depth) / (1.0 - random)).log10(),
+            runs: Vec::new(),
+        }
+    }
+}
+
+impl Default for Weights {
+    /// German in depth, about +1.9 decibans a repeat and -0.1 a mismatch, and
+    /// a rough extra for bigram, trigram and tetragram runs
+    fn default() -> Self {
+        Self {
+            runs: vec![(2, 1.0), (3, 3.0), (4, 6.0)],
+            ..Self::from_rates(1.0 / 17.0, 1.0 / 26.0)
+        }
+    }
+}
+
+/// Two ciphertexts laid over each other at one offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlap {
+    /// Letter `i` of the first text lies over letter `i - offset` of the
+    /// second
+    pub offset: isize,
+    /// Letters the two texts have in common
+    pub length: usize,
+    pub repeats: usize,
+    /// Runs of two or more repeats, as letters of the first text
+    pub runs: Vec<Range<usize>>,
+    /// Evidence for depth in decibans
+    pub score: f64,
+}
+
+impl Overlap {
+    /// Length of the longest run of repeats, 0 without any
+    pub fn longest_run(&self) -> usize {
+        self.runs.iter().map(|run| run.len()).max().unwrap_or(0)
+    }
+}
+
+/// Lay `second` over `first` at every offset where they have a letter in
+/// common, in order of offset
+pub fn overlay(
+    first: &str,
+    second: &str,
+    weights: &Weights,
+) -> Result<Vec<Overlap>, AnalysisError> {
+    let (first, second) = (letters(first)?, letters(second)?);
+    if first.is_empty() || second.is_empty() {
+        return Err(AnalysisError::TooShort);
+    }
+
+    let offsets = 1 - second.len() as isize..first.len() as isize;
+    Ok(offsets
+        .map(|offset| {
+            let start = offset.max(0) as usize;
+            let end = first.len().min((second.len() as isize + offset) as usize);
+            let mut overlap =
+                Overlap { offset, length: end - start, repeats: 0, runs: Vec::new(), score: 0.0 };
+            let mut run = start..start;
+            for i in start..=end {
+                let repeat = i < end && first[i] == second[(i as isize - offset) as usize];
+                if repeat {
+                    overlap.repeats += 1;
+                    run.end = i + 1;
+                    continue;
+                }
+                if run.len() >= 2 {
+                    let extra = weights.runs.iter().filter(|(len, _)| *len <= run.len());
+                    overlap.score += extra.max_by_key(|(len, _)| *len).map_or(0.0, |(_, db)| *db);
+                    overlap.runs.push(run);
+                }
+                run = i + 1..i + 1;
+            }
+            overlap.score += overlap.repeats as f64 * weights.repeat
+                + (overlap.length - overlap.repeats) as f64 * weights.mismatch;
+            overlap
+        })
+        .collect())
+}
+
+/// Two letters of message keys for the same rotor, `to` coming `letters`
+/// after `from` in the alphabet of the rotor. For the right rotor the keys
+/// agree otherwise, for the middle rotor in the left letter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Distance {
+    pub from: EnigmaAlphabet,
+    pub to: EnigmaAlphabet,
+    pub letters: usize,
+}
+
+impl Distance {
+    /// Distance from the best overlay of the message with right rotor letter
+    /// `first` over the one with `second`
+    pub fn from_overlap(first: EnigmaAlphabet, second: EnigmaAlphabet, overlap: &Overlap) -> Self {
+        match overlap.offset >= 0 {
+            true => Self { from: first, to: second, letters: overlap.offset as usize },
+            false => Self { from: second, to: first, letters: overlap.offset.unsigned_abs() },
+        }
+    }
+
+    /// Distance between the middle rotor letters `middle` of two message keys
+    /// with the same left letter, from their best overlay. `rotor` stands on
+    /// the right at the window letters `right` of the two keys, e.g. as found
+    /// with [`Chain::starts`]. `None` if the offset does not take the right
+    /// rotor from one window letter to the other, or if it leaves the middle
+    /// rotor where it was.
+    pub fn from_middle_overlap(
+        middle: [EnigmaAlphabet; 2],
+        right: [EnigmaAlphabet; 2],
+        rotor: RotorId,
+        overlap: &Overlap,
+    ) -> Option<Self> {
+        let (from, to, right, letters) = match overlap.offset >= 0 {
+            true => (middle[0], middle[1], right, overlap.offset as usize),
+            false => (middle[1], middle[0], [right[1], right[0]], overlap.offset.unsigned_abs()),
+        };
+        let mut rotor = rotor.rotor();
+        rotor.set(right[0]);
+        let steps = (0..letters).filter(|_| rotor.turn()).count();
+        match rotor.position() == right[1] && (1..26).contains(&steps) {
+            true => Some(Self { from, to, letters: steps }),
+            false => None,
+        }
+    }
+}
+
+/// Letters in alphabet order relative to each other, as far as the distances
+/// tell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chain {
+    /// Letters by distance from the first one, `None` where unknown
+    pub letters: [Option<EnigmaAlphabet>; 26],
+    /// Positions, counted like `letters`, from which the rotor cannot carry
+    /// its left neighbour
+    pub no_turnover: [bool; 26],
+}
+
+impl Chain {
+    /// Window letters the first letter of the chain can stand for with
+    /// `rotor` in the place of its letters, given where its notches cannot be
+    pub fn starts(&self, rotor: RotorId) -> Vec<EnigmaAlphabet> {
+        let mut rotor = rotor.rotor();
+        (0..26)
+            .map(EnigmaAlphabet::new)
+            .filter(|&start| {
+                (0..26).filter(|&position| self.no_turnover[usize::from(position)]).all(
+                    |position| {
+                        rotor.set(start + position);
+                        !rotor.at_turnover()
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Chain {
+    /// E.g. `K-----P----X--------------`, `-` for unknown letters
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letters: String = self
+            .letters
+            .iter()
+            .map(|letter| letter.map_or('-', |letter| letter.to_char()))
+            .collect();
+        write!(f, "{letters}")
+    }
+}
+
+/// Put `distances` together into chains
+pub fn scritchmus(distances: &[Distance]) -> Result<Vec<Chain>, AnalysisError> {
+    // chain and position of each letter
+    let mut placed: [Option<(usize, u8)>; 26] = [None; 26];
+    let mut chains = 0;
+    for (n, distance) in distances.iter().enumerate() {
+        let contradiction = AnalysisError::InconsistentDistance(n);
+        if !(1..26).contains(&distance.letters) || distance.from == distance.to {
+            return Err(contradiction);
+        }
+        let letters = distance.letters as u8;
+        let (from, to) = (usize::from(distance.from), usize::from(distance.to));
+        match (placed[from], placed[to]) {
+            (None, None) => {
+                placed[from] = Some((chains, 0));
+                placed[to] = Some((chains, letters));
+                chains += 1;
+            }
+            (Some((chain, position)), None) => {
+                placed[to] = Some((chain, (position + letters) % 26))
+            }
+            (None, Some((chain, position))) => {
+                placed[from] = Some((chain, (position + 26 - letters) % 26))
+            }
+            (Some((chain, position)), Some((other, other_position))) => {
+                // move the other chain so that `to` comes `letters` after `from`
+                let shift = (position + letters + 26 - other_position) % 26;
+                if chain == other && shift != 0 {
+                    return Err(contradiction);
+                }
+                for (c, p) in placed.iter_mut().flatten() {
+                    if *c == other {
+                        *c = chain;
+                        *p = (*p + shift) % 26;
+                    }
+                }
+            }
+        }
+
+        // no two letters at the same place
+        let mut taken = [false; 26];
+        let (chain, _) = placed[from].expect("just placed");
+        for &(_, position) in placed.iter().flatten().filter(|(c, _)| *c == chain) {
+            if core::mem::replace(&mut taken[usize::from(position)], true) {
+                return Err(contradiction);
+            }
+        }
+    }
+
+    // each chain counted from the first of its letters the distances name
+    let mut found: Vec<(usize, u8, Chain)> = Vec::new();
+    for distance in distances {
+        let (chain, from) = placed[usize::from(distance.from)].expect("every letter was placed");
+        if !found.iter().any(|(c, _, _)| *c == chain) {
+            let mut letters = [None; 26];
+            for (letter, place) in placed.iter().enumerate() {
+                if let Some((_, position)) = place.filter(|(c, _)| *c == chain) {
+                    letters[usize::from((position + 26 - from) % 26)] =
+                        Some(EnigmaAlphabet::new(letter as u8));
+                }
+            }
+            found.push((chain, from, Chain { letters, no_turnover: [false; 26] }));
+        }
+        let (_, origin, chain) =
+            found.iter_mut().find(|(c, _, _)| *c == chain).expect("just pushed");
+        let start = usize::from((from + 26 - *origin) % 26);
+        for step in 0..distance.letters {
+            chain.no_turnover[(start + step) % 26] = true;
+        }
+    }
+    Ok(found.into_iter().map(|(_, _, chain)| chain).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_day;
+    use crate::conventions::Conventions;
+    use crate::conventions::Service;
+    use crate::core::engine::Enigma;
+    use crate::historical_machines::model::Model;
+
+    fn encrypt(plaintext: &str, grundstellung: &str) -> String {
+        let mut enigma =
+            Enigma::from_key(Model::EnigmaI, &test_day::key_at(grundstellung)).unwrap();
+        letters(plaintext)
+            .unwrap()
+            .into_iter()
+            .map(|letter| enigma.encode(letter).to_char())
+            .collect()
+    }
+
+    #[test]
+    fn test_overlay_finds_depth() {
+        let german =
+            Conventions::new(Service::Heer).encode(include_str!("corpus/german.txt")).unwrap();
+        // the keys differ by six letters of the right rotor
+        let first = encrypt(&german[..1200], "ACB");
+        let second = encrypt(&german[3000..4200], "ACH");
+
+        let overlaps = overlay(&first, &second, &Weights::default()).unwrap();
+        assert_eq!(overlaps.len(), 2399);
+        assert_eq!((overlaps[0].offset, overlaps[0].length), (-1199, 1));
+        let best = overlaps.iter().max_by(|a, b| a.score.total_cmp(&b.score)).unwrap();
+        assert_eq!(best.offset, 6);
+        assert!(best.repeats * 20 > best.length, "{best:?}");
+        assert!(best.score > 30.0, "{best:?}");
+        for run in &best.runs {
+            assert!(run.len() >= 2);
+            assert_eq!(first[run.clone()], second[run.start - 6..run.end - 6]);
+        }
+
+        let distance =
+            Distance::from_overlap('P'.into(), 'K'.into(), &Overlap { offset: -6, ..best.clone() });
+        assert_eq!(distance, Distance { from: 'K'.into(), to: 'P'.into(), letters: 6 });
+        assert_eq!(overlay("", "AB", &Weights::default()), Err(AnalysisError::TooShort));
+    }
+
+    #[test]
+    fn test_middle_rotor() {
+        let german =
+            Conventions::new(Service::Heer).encode(include_str!("corpus/german.txt")).unwrap();
+        // 60 letters on, the right rotor III has passed its notch V twice
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &test_day::key_at("ACB")).unwrap();
+        for _ in 0..60 {
+            enigma.encode('A');
+        }
+        assert_eq!(enigma.positions(), ['A', 'E', 'J'].map(EnigmaAlphabet::from));
+        let first = encrypt(&german[..1200], "ACB");
+        let second = encrypt(&german[3000..4200], "AEJ");
+
+        let overlaps = overlay(&first, &second, &Weights::default()).unwrap();
+        let best = overlaps.iter().max_by(|a, b| a.score.total_cmp(&b.score)).unwrap();
+        assert_eq!(best.offset, 60);
+        let middle = ['C', 'E'].map(EnigmaAlphabet::from);
+        let distance = Distance::from_middle_overlap(
+            middle,
+            ['B', 'J'].map(EnigmaAlphabet::from),
+            RotorId::III,
+            best,
+        );
+        assert_eq!(distance, Some(Distance { from: 'C'.into(), to: 'E'.into(), letters: 2 }));
+        let swapped = Distance::from_middle_overlap(
+            [middle[1], middle[0]],
+            ['J', 'B'].map(EnigmaAlphabet::from),
+            RotorId::III,
+            &Overlap { offset: -60, ..best.clone() },
+        );
+        assert_eq!(swapped, distance);
+
+        // the right rotor does not get from B to K in 60 letters
+        let right = ['B', 'K'].map(EnigmaAlphabet::from);
+        assert_eq!(Distance::from_middle_overlap(middle, right, RotorId::III, best), None);
+        // nor does the middle rotor move in 6 letters from B
+        let right = ['B', 'H'].map(EnigmaAlphabet::from);
+        let short = Overlap { offset: 6, ..best.clone() };
+        assert_eq!(Distance::from_middle_overlap(middle, right, RotorId::III, &short), None);
+
+        // the middle rotor I went from C to H without carrying the left one,
+        // so its notch Q is not among C to G
+        let chains = scritchmus(&[
+            distance.unwrap(),
+            Distance { from: 'E'.into(), to: 'H'.into(), letters: 3 },
+        ])
+        .unwrap();
+        assert_eq!(chains[0].to_string(), "C-E--H--------------------");
+        let starts: String =
+            chains[0].starts(RotorId::I).iter().map(|start| start.to_char()).collect();
+        assert_eq!(starts, "ABCDEFGHIJKLRSTUVWXYZ");
+    }
+
+    #[test]
+    fn test_weights() {
+        let weights = Weights::from_rates(1.0 / 17.0, 1.0 / 26.0);
+        assert!((weights.repeat - 1.845).abs() < 0.001);
+        assert!((weights.mismatch + 0.093).abs() < 0.001);
+
+        let overlaps = overlay("ABCDE", "ABCDX", &Weights::default()).unwrap();
+        let aligned = overlaps.iter().find(|overlap| overlap.offset == 0).unwrap();
+        assert_eq!((aligned.repeats, aligned.longest_run()), (4, 4));
+        let expected = 4.0 * weights.repeat + weights.mismatch + 6.0;
+        assert!((aligned.score - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_scritchmus() {
+        // enciphered right rotor letters K P X D standing for windows B H M T
+        let distance =
+            |from: char, to: char, letters| Distance { from: from.into(), to: to.into(), letters };
+        let distances = [
+            distance('K', 'P', 6),
+            distance('A', 'C', 3),
+            distance('P', 'X', 5),
+            distance('X', 'D', 7),
+        ];
+        let chains = scritchmus(&distances).unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].to_string(), "K-----P----X------D-------");
+        assert_eq!(chains[1].to_string(), "A--C----------------------");
+
+        // the notch of III, at V, is not among the 18 letters from the first
+        let starts: String =
+            chains[0].starts(RotorId::III).iter().map(|start| start.to_char()).collect();
+        assert_eq!(starts, "ABCDWXYZ");
+        // two notches 13 apart cannot both be missed
+        assert_eq!(chains[0].starts(RotorId::VI), []);
+
+        let contradiction = [distance('K', 'P', 6), distance('P', 'X', 5), distance('K', 'X', 12)];
+        assert_eq!(scritchmus(&contradiction), Err(AnalysisError::InconsistentDistance(2)));
+        let same_place = [distance('A', 'B', 3), distance('C', 'B', 3)];
+        assert_eq!(scritchmus(&same_place), Err(AnalysisError::InconsistentDistance(1)));
+        assert_eq!(
+            scritchmus(&[distance('A', 'B', 26)]),
+            Err(AnalysisError::InconsistentDistance(0))
+        );
+    }
+}
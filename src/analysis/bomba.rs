@@ -0,0 +1,181 @@
+//! Polish bomba
+//!
+//! The bomba kryptologiczna of November 1938 attacked the same indicators as
+//! the Zygalski sheets, a clear start followed by the doubled message key. It
+//! waited for three messages of a day with a female of the same letter at the
+//! same pair of positions, say 1 and 4.
+//!
+//! For each of them a pair of machines stood at the clear start, one moved on
+//! to the first letter of the pair and the other to the second. The six
+//! machines turned together through all 17,576 positions of a wheel order,
+//! which is the same as trying every ring setting. Where the letter typed on
+//! the first machine of every pair came back out of the second, a female of
+//! that letter was possible for all three messages and the bomba stopped.
+//!
+//! The letter only comes back if the plugboard leaves it alone, and a bomba
+//! misses the day otherwise. A wheel order gives about one false stop, checked
+//! by hand like those of the British bombe. As with the sheets, the middle
+//! rotor is taken not to move during the indicator.
+
+use super::machine;
+use super::zygalski::Indicator;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::parts::plugboard::Plugboard;
+use crate::key::DailyKey;
+use crate::key::Ringstellung;
+use crate::key::Steckerverbindungen;
+use crate::key::Walzenlage;
+
+/// Where the bomba stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stop {
+    pub walzenlage: Walzenlage,
+    pub rings: [u8; 3],
+    /// Rotor cores at the clear start of the first message
+    pub cores: [EnigmaAlphabet; 3],
+}
+
+impl Stop {
+    /// The rotor part of the daily key, no plugs and no Grundstellung since
+    /// every message has its own
+    pub fn daily_key(&self) -> DailyKey {
+        DailyKey {
+            walzenlage: self.walzenlage,
+            ringstellung: Ringstellung(self.rings),
+            steckerverbindungen: Steckerverbindungen::default(),
+            grundstellung: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bomba {
+    indicators: [Indicator; 3],
+    /// 0 for positions 1 and 4, 1 for 2 and 5, 2 for 3 and 6
+    pair: usize,
+    letter: EnigmaAlphabet,
+}
+
+impl Bomba {
+    /// Set up for three indicators with a female of the same letter at `pair`
+    pub fn new(indicators: [Indicator; 3], pair: usize) -> Result<Self, AnalysisError> {
+        let letter = match indicators[0].females().any(|female| female.pair == pair) {
+            true => indicators[0].doubled[pair],
+            false => return Err(AnalysisError::NoFemale(0)),
+        };
+        for (n, indicator) in indicators.iter().enumerate() {
+            if indicator.doubled[pair] != letter || indicator.doubled[pair + 3] != letter {
+                return Err(AnalysisError::NoFemale(n));
+            }
+        }
+        Ok(Self { indicators, pair, letter })
+    }
+
+    /// The letter of the females
+    pub fn letter(&self) -> EnigmaAlphabet {
+        self.letter
+    }
+
+    /// Run through every position of `walzenlage`
+    pub fn run(&self, walzenlage: Walzenlage) -> Vec<Stop> {
+        // scrambler at every position of the cores, the machines only look
+        // it up
+        let scramblers: Vec<[EnigmaAlphabet; 26]> = (0..26 * 26 * 26)
+            .map(|position| {
+                let cores = [position / 676, position / 26 % 26, position % 26]
+                    .map(|p| EnigmaAlphabet::new(p as u8));
+                machine(walzenlage, [0; 3], cores, Plugboard::default()).permutation()
+            })
+            .collect();
+        let scrambler = |[left, middle, right]: [EnigmaAlphabet; 3], steps: usize| {
+            let right = right + (steps % 26) as u8;
+            &scramblers[usize::from(left) * 676 + usize::from(middle) * 26 + usize::from(right)]
+        };
+
+        let mut stops = Vec::new();
+        for rings in 0..26 * 26 * 26 {
+            let rings = [rings / 676, rings / 26 % 26, rings % 26].map(|ring| ring as u8);
+            let cores = |indicator: &Indicator| {
+                let mut cores = indicator.start;
+                for (core, ring) in cores.iter_mut().zip(rings) {
+                    *core -= ring;
+                }
+                cores
+            };
+            let stopped = self.indicators.iter().all(|indicator| {
+                // the rotor steps before the first letter
+                let cores = cores(indicator);
+                let first = scrambler(cores, self.pair + 1)[usize::from(self.letter)];
+                scrambler(cores, self.pair + 4)[usize::from(first)] == self.letter
+            });
+            if stopped {
+                stops.push(Stop { walzenlage, rings, cores: cores(&self.indicators[0]) });
+            }
+        }
+        stops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_day;
+    use crate::analysis::test_day::key;
+    use crate::core::engine::Enigma;
+    use crate::historical_machines::model::Model;
+    use crate::key::Grundstellung;
+
+    /// Three indicators of a day with a female at 1 and 4 of the same
+    /// unsteckered letter
+    fn indicators() -> [Indicator; 3] {
+        let mut found: Vec<Vec<Indicator>> = vec![Vec::new(); 26];
+        test_day::indicators(1938)
+            .filter(|indicator| {
+                let letter = indicator.doubled[0];
+                indicator.doubled[3] == letter
+                    && !key().steckerverbindungen.to_string().contains(letter.to_char())
+            })
+            .find_map(|indicator| {
+                let same = &mut found[usize::from(indicator.doubled[0])];
+                same.push(indicator);
+                <[Indicator; 3]>::try_from(same.as_slice()).ok()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bomba_stops_at_the_key() {
+        let bomba = Bomba::new(indicators(), 0).unwrap();
+        let mut stops = Vec::new();
+        for walzenlage in ["B I II III", "B II I III", "B III I II"] {
+            stops.extend(bomba.run(walzenlage.parse().unwrap()));
+        }
+        assert!(stops.len() <= 10, "{stops:?}");
+        let stop = stops
+            .iter()
+            .find(|stop| stop.walzenlage == key().walzenlage)
+            .expect("the bomba stops at the true ring setting");
+        assert_eq!(stop.rings, key().ringstellung.0);
+
+        // the cores at the stop give the window of the first clear start
+        let indicator = indicators()[0];
+        let daily_key = DailyKey { grundstellung: Some(Grundstellung(indicator.start)), ..key() };
+        let enigma = Enigma::from_key(Model::EnigmaI, &daily_key).unwrap();
+        let mut windows = stop.cores;
+        for (window, ring) in windows.iter_mut().zip(stop.rings) {
+            *window += ring;
+        }
+        assert_eq!(windows, enigma.positions());
+        assert_eq!(stop.daily_key().ringstellung, key().ringstellung);
+    }
+
+    #[test]
+    fn test_bomba_needs_three_females() {
+        let [first, second, third] = indicators();
+        assert_eq!(Bomba::new([first, second, third], 1), Err(AnalysisError::NoFemale(0)));
+        let other: Indicator = "ABC DEFGHI".parse().unwrap();
+        assert_eq!(Bomba::new([first, other, third], 0), Err(AnalysisError::NoFemale(1)));
+        assert_eq!(Bomba::new([first, second, third], 0).unwrap().letter(), first.doubled[0]);
+    }
+}
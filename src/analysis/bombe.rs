@@ -0,0 +1,342 @@
+//! Turing–Welchman Bombe
+//!
+//! A menu of plaintext/ciphertext letter pairs is wired into unsteckered
+//! scramblers, one for each pair at the offset of the pair. The test register
+//! holds 26 wires for the central letter of the menu, wire `x` standing for
+//! the hypothesis "the central letter is steckered to `x`". Current is put on
+//! one wire and spreads through the scramblers: if the central letter is
+//! steckered to `x`, the letter at the other end of a scrambler is steckered
+//! to whatever the scrambler turns `x` into. Welchman's diagonal board adds the
+//! symmetry of the plugboard: `A` steckered to `x` means `x` steckered to `A`.
+//!
+//! With a wrong rotor position the current reaches all 26 wires. The machine
+//! stops when it does not:
+//!
+//! - a single live wire is the stecker of the central letter,
+//! - a single dead wire is the stecker of the central letter, every other
+//!   hypothesis having led to a contradiction,
+//! - anything else is a stop without a clear stecker.
+//!
+//! Unlike the real machine, which assumed that only the right rotor moves
+//! within the menu, the scramblers step exactly like the [`Enigma`] engine,
+//! turnover and double step included, for the given ring settings.
+
+use core::fmt;
+
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::engine::Enigma;
+use crate::core::parts::plugboard::Plugboard;
+use crate::historical_machines::model::RotorId;
+use crate::key::Walzenlage;
+
+/// One pair of the menu: `plain` enciphers to `cipher` at `offset` letters
+/// into the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MenuEdge {
+    pub offset: usize,
+    pub plain: EnigmaAlphabet,
+    pub cipher: EnigmaAlphabet,
+}
+
+impl MenuEdge {
+    /// Menu from consecutive pairs, the first at `offset` 0
+    pub fn from_pairs(pairs: &[(EnigmaAlphabet, EnigmaAlphabet)]) -> Vec<Self> {
+        (0..).zip(pairs).map(|(offset, &(plain, cipher))| Self { offset, plain, cipher }).collect()
+    }
+}
+
+/// Why the machine stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// Only the input wire of the test register is live
+    OneLive,
+    /// Every wire of the test register but one is live
+    OneDead,
+    /// The given number of wires is live, which names no stecker
+    Partial(u8),
+}
+
+/// A rotor position at which the test register did not fill up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stop {
+    /// Window letters before the first letter of the menu is typed
+    pub positions: [EnigmaAlphabet; 3],
+    pub reason: StopReason,
+    /// Stecker of the central letter, unless the stop is [`StopReason::Partial`]
+    pub stecker: Option<(EnigmaAlphabet, EnigmaAlphabet)>,
+    /// Steckers of menu letters implied by the central stecker, each letter
+    /// with its partner in alphabetical order
+    pub steckers: Vec<(EnigmaAlphabet, EnigmaAlphabet)>,
+    /// Offset of the menu letter at which the middle rotor steps, if it does
+    pub middle_step: Option<usize>,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [l, m, r] = self.positions.map(|letter| letter.to_char());
+        write!(f, "{l}{m}{r}: ")?;
+        match (self.reason, self.stecker) {
+            (StopReason::OneLive, Some((a, b))) => {
+                write!(f, "only {}/{} is live", a.to_char(), b.to_char())?
+            }
+            (StopReason::OneDead, Some((a, b))) => {
+                write!(f, "all but {}/{} are live", a.to_char(), b.to_char())?
+            }
+            (StopReason::Partial(live), _) => write!(f, "{live} of 26 wires are live")?,
+            (_, None) => write!(f, "no stecker")?,
+        }
+        if !self.steckers.is_empty() {
+            let steckers: Vec<String> = self
+                .steckers
+                .iter()
+                .map(|(a, b)| format!("{}{}", a.to_char(), b.to_char()))
+                .collect();
+            write!(f, ", steckers {}", steckers.join(" "))?;
+        }
+        if let Some(offset) = self.middle_step {
+            write!(f, ", middle rotor steps at offset {offset}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Bombe {
+    walzenlage: Walzenlage,
+    rings: [u8; 3],
+    edges: Vec<MenuEdge>,
+    /// For every letter the letters it shares a menu pair with, and the index
+    /// of the pair
+    links: [Vec<(usize, usize)>; 26],
+    /// Menu letter the test register is attached to
+    central: EnigmaAlphabet,
+    /// Wire of the test register current is put on
+    input: EnigmaAlphabet,
+}
+
+impl Bombe {
+    /// Bombe for one wheel order. The test register goes on the letter with
+    /// the most menu pairs.
+    pub fn new(walzenlage: Walzenlage, edges: Vec<MenuEdge>) -> Result<Self, AnalysisError> {
+        let mut links: [Vec<(usize, usize)>; 26] = Default::default();
+        for (i, edge) in edges.iter().enumerate() {
+            let (plain, cipher) = (usize::from(edge.plain), usize::from(edge.cipher));
+            links[plain].push((cipher, i));
+            links[cipher].push((plain, i));
+        }
+        let central =
+            (0..26).max_by_key(|&i| (links[i].len(), usize::MAX - i)).expect("26 letters");
+        if links[central].is_empty() {
+            return Err(AnalysisError::EmptyMenu);
+        }
+        let central = EnigmaAlphabet::new(central as u8);
+        let input = central + 1;
+        Ok(Self { walzenlage, rings: [0; 3], edges, links, central, input })
+    }
+
+    /// Ring settings the scramblers step with, `[0; 3]` by default
+    pub fn with_rings(mut self, rings: [u8; 3]) -> Self {
+        self.rings = rings;
+        self
+    }
+
+    /// Attach the test register to `central` and put current on `input`
+    pub fn with_test_register(mut self, central: EnigmaAlphabet, input: EnigmaAlphabet) -> Self {
+        self.central = central;
+        self.input = input;
+        self
+    }
+
+    pub fn central(&self) -> EnigmaAlphabet {
+        self.central
+    }
+
+    /// Every rotor position where the machine stops, in order `AAA` to `ZZZ`
+    pub fn run(&self) -> Vec<Stop> {
+        let mut enigma = self.scrambler();
+        (0..26 * 26 * 26)
+            .filter_map(|i| {
+                let positions =
+                    [i / 676, i / 26 % 26, i % 26].map(|p| EnigmaAlphabet::new(p as u8));
+                self.test_with(&mut enigma, positions)
+            })
+            .collect()
+    }
+
+    /// Run the menu at one rotor position
+    pub fn test(&self, positions: [EnigmaAlphabet; 3]) -> Option<Stop> {
+        self.test_with(&mut self.scrambler(), positions)
+    }
+
+    fn scrambler(&self) -> Enigma {
+        let reflector = self.walzenlage.reflector.reflector();
+        let mut enigma = Enigma::new(
+            reflector,
+            self.walzenlage.rotors.map(RotorId::rotor),
+            Plugboard::default(),
+        );
+        enigma.set_rings(self.rings);
+        enigma
+    }
+
+    fn test_with(&self, enigma: &mut Enigma, positions: [EnigmaAlphabet; 3]) -> Option<Stop> {
+        let (scramblers, middle_step) = self.scramblers(enigma, positions);
+        let live = self.propagate(&scramblers, self.central, self.input, true);
+        let count = live[usize::from(self.central)].count_ones() as u8;
+        let (reason, stecker) = match count {
+            26 => return None,
+            1 => (StopReason::OneLive, Some(self.input)),
+            25 => {
+                let dead = (!live[usize::from(self.central)]).trailing_zeros();
+                (StopReason::OneDead, Some(EnigmaAlphabet::new(dead as u8)))
+            }
+            _ => (StopReason::Partial(count), None),
+        };
+
+        let steckers = match stecker {
+            Some(stecker) => self.steckers(&scramblers, stecker),
+            None => Vec::new(),
+        };
+        Some(Stop {
+            positions,
+            reason,
+            stecker: stecker.map(|stecker| (self.central, stecker)),
+            steckers,
+            middle_step,
+        })
+    }
+
+    /// Scrambler substitution for every menu pair, and the offset at which the
+    /// middle rotor steps
+    fn scramblers(
+        &self,
+        enigma: &mut Enigma,
+        positions: [EnigmaAlphabet; 3],
+    ) -> (Vec<[EnigmaAlphabet; 26]>, Option<usize>) {
+        let last = self.edges.iter().map(|edge| edge.offset).max().unwrap_or(0);
+        enigma.set_positions(positions);
+        let mut substitutions = Vec::with_capacity(last + 1);
+        let mut middle_step = None;
+        for offset in 0..=last {
+            let middle = enigma.positions()[1];
+            enigma.step();
+            if middle_step.is_none() && enigma.positions()[1] != middle {
+                middle_step = Some(offset);
+            }
+            substitutions.push(enigma.permutation());
+        }
+        let scramblers = self.edges.iter().map(|edge| substitutions[edge.offset]).collect();
+        (scramblers, middle_step)
+    }
+
+    /// Live wires of every register, as bit sets, after putting current on
+    /// wire `wire` of register `letter`. With `until_full` propagation ends
+    /// as soon as the test register is all live.
+    fn propagate(
+        &self,
+        scramblers: &[[EnigmaAlphabet; 26]],
+        letter: EnigmaAlphabet,
+        wire: EnigmaAlphabet,
+        until_full: bool,
+    ) -> [u32; 26] {
+        const ALL: u32 = (1 << 26) - 1;
+        let central = usize::from(self.central);
+        let mut live = [0u32; 26];
+        let mut pending = vec![(usize::from(letter), usize::from(wire))];
+        while let Some((letter, wire)) = pending.pop() {
+            if live[letter] & 1 << wire != 0 {
+                continue;
+            }
+            live[letter] |= 1 << wire;
+            if until_full && live[central] == ALL {
+                break;
+            }
+            // diagonal board
+            pending.push((wire, letter));
+            for &(other, edge) in &self.links[letter] {
+                pending.push((other, usize::from(scramblers[edge][wire])));
+            }
+        }
+        live
+    }
+
+    /// Steckers of the menu letters that follow from the central letter being
+    /// steckered to `stecker`
+    fn steckers(
+        &self,
+        scramblers: &[[EnigmaAlphabet; 26]],
+        stecker: EnigmaAlphabet,
+    ) -> Vec<(EnigmaAlphabet, EnigmaAlphabet)> {
+        let live = self.propagate(scramblers, self.central, stecker, false);
+        let mut steckers: Vec<_> = self
+            .edges
+            .iter()
+            .flat_map(|edge| [edge.plain, edge.cipher])
+            .filter(|&letter| live[usize::from(letter)].count_ones() == 1)
+            .map(|letter| {
+                let partner = EnigmaAlphabet::new(live[usize::from(letter)].trailing_zeros() as u8);
+                if letter.to_u8() < partner.to_u8() {
+                    (letter, partner)
+                } else {
+                    (partner, letter)
+                }
+            })
+            .collect();
+        steckers.sort_by_key(|(a, b)| (a.to_u8(), b.to_u8()));
+        steckers.dedup();
+        steckers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::letters;
+    use crate::core::encoder::Encoder;
+    use crate::historical_machines::model::Model;
+    use crate::key::DailyKey;
+
+    const KEY: &str = "B II IV V 01 01 01 AV BS CG DL FU HZ IN KM OW RX BLW";
+    const CRIB: &str = "WETTERVORHERSAGEBISKAYA";
+
+    fn menu() -> Vec<MenuEdge> {
+        let key: DailyKey = KEY.parse().unwrap();
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &key).unwrap();
+        let pairs: Vec<_> =
+            letters(CRIB).unwrap().into_iter().map(|plain| (plain, enigma.encode(plain))).collect();
+        MenuEdge::from_pairs(&pairs)
+    }
+
+    #[test]
+    fn test_bombe_finds_key() {
+        let key: DailyKey = KEY.parse().unwrap();
+        let bombe = Bombe::new(key.walzenlage, menu()).unwrap();
+        let stops = bombe.run();
+        assert!(stops.len() < 20, "{} stops", stops.len());
+
+        let stop = stops
+            .iter()
+            .find(|stop| stop.positions == ['B', 'L', 'W'].map(EnigmaAlphabet::from))
+            .unwrap();
+        let plugboard = Plugboard::from(key.steckerverbindungen);
+        let (central, stecker) = stop.stecker.unwrap();
+        assert_eq!(plugboard.encode(central), stecker);
+        assert!(!stop.steckers.is_empty());
+        for &(a, b) in &stop.steckers {
+            assert_eq!(plugboard.encode(a), b);
+        }
+        // W X Y Z: the fourth key press turns the middle rotor
+        assert_eq!(stop.middle_step, Some(3));
+        assert!(stop.to_string().starts_with("BLW: "));
+        assert!(stop.to_string().ends_with("middle rotor steps at offset 3"));
+    }
+
+    #[test]
+    fn test_bombe_positions() {
+        let key: DailyKey = KEY.parse().unwrap();
+        let bombe = Bombe::new(key.walzenlage, menu()).unwrap();
+        assert!(bombe.test(['B', 'L', 'W'].map(EnigmaAlphabet::from)).is_some());
+        assert_eq!(Bombe::new(key.walzenlage, Vec::new()).unwrap_err(), AnalysisError::EmptyMenu);
+    }
+}
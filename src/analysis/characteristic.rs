@@ -0,0 +1,337 @@
+//! Rejewski's characteristic method
+//!
+//! Before May 1940 every message began with its key enciphered twice at the
+//! Grundstellung of the day (see [`doubled_indicator`]). Call `A` to `F` the
+//! permutations of the machine at the six letters of the indicator. The first
+//! and fourth letter both encipher the same key letter, so `AD` takes the
+//! first indicator letter to the fourth, `BE` the second to the fifth and `CF`
+//! the third to the sixth. With enough indicators of a day the three products
+//! are known completely.
+//!
+//! The plugboard only relabels the letters of a product, so the lengths of its
+//! cycles depend on the rotors alone. The cycle lengths of the three products
+//! are the characteristic of the day. The [`Catalogue`] lists the
+//! characteristic of every start position of every wheel order, with the rings
+//! at `A` and the plugboard left out. Looking the day up gives the few wheel
+//! orders and positions of the rotor cores at the Grundstellung.
+//!
+//! As with the card catalogue, a day whose indicators move the middle rotor
+//! differently from the rings at `A` is not found.
+//!
+//! [`doubled_indicator`]: crate::procedure::doubled_indicator
+
+use core::fmt;
+use std::collections::HashMap;
+
+use super::letters;
+use super::machine;
+use super::scramblers;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::parts::plugboard::Plugboard;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::key::Ringstellung;
+use crate::key::Steckerverbindungen;
+use crate::key::Walzenlage;
+
+/// Product of two indicator permutations, as far as the indicators show it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Product([Option<EnigmaAlphabet>; 26]);
+
+impl Product {
+    /// Image of `letter`, if known
+    pub fn get(&self, letter: EnigmaAlphabet) -> Option<EnigmaAlphabet> {
+        self.0[usize::from(letter)]
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.0.iter().all(Option::is_some)
+    }
+
+    /// Cycles of a complete product, longest first and each starting at its
+    /// first letter in the alphabet
+    pub fn cycles(&self) -> Option<Vec<Vec<EnigmaAlphabet>>> {
+        if !self.is_complete() {
+            return None;
+        }
+        let mut seen = [false; 26];
+        let mut cycles = Vec::new();
+        for start in 0..26 {
+            let mut cycle = Vec::new();
+            let mut letter = EnigmaAlphabet::new(start as u8);
+            while !seen[usize::from(letter)] {
+                seen[usize::from(letter)] = true;
+                cycle.push(letter);
+                letter = self.get(letter).expect("product is complete");
+            }
+            if !cycle.is_empty() {
+                cycles.push(cycle);
+            }
+        }
+        cycles.sort_by_key(|cycle| core::cmp::Reverse(cycle.len()));
+        Some(cycles)
+    }
+
+    fn from_permutations(first: &[EnigmaAlphabet; 26], second: &[EnigmaAlphabet; 26]) -> Self {
+        Self(core::array::from_fn(|i| Some(second[usize::from(first[i])])))
+    }
+}
+
+impl fmt::Display for Product {
+    /// Cycle notation, e.g. `(AK)(BZ)...`, known parts of an incomplete
+    /// product as `A→K`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cycles() {
+            Some(cycles) => {
+                for cycle in cycles {
+                    let cycle: String = cycle.iter().map(|letter| letter.to_char()).collect();
+                    write!(f, "({cycle})")?;
+                }
+            }
+            None => {
+                let known = (0..26).filter_map(|i| {
+                    let letter = EnigmaAlphabet::new(i as u8);
+                    self.get(letter).map(|image| (letter, image))
+                });
+                for (n, (letter, image)) in known.enumerate() {
+                    let separator = if n == 0 { "" } else { " " };
+                    write!(f, "{separator}{}→{}", letter.to_char(), image.to_char())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The products `AD`, `BE` and `CF` of a day's doubled indicators
+///
+/// Fails on an indicator that is not six letters, or that maps a letter
+/// differently from an earlier one, as a garbled indicator would.
+pub fn products<'a>(
+    indicators: impl IntoIterator<Item = &'a str>,
+) -> Result<[Product; 3], AnalysisError> {
+    let mut products = [Product([None; 26]); 3];
+    let mut inverses = [Product([None; 26]); 3];
+    for (n, indicator) in indicators.into_iter().enumerate() {
+        let indicator = letters(indicator)?;
+        if indicator.len() != 6 {
+            return Err(AnalysisError::InvalidIndicator(n));
+        }
+        for (i, (product, inverse)) in products.iter_mut().zip(&mut inverses).enumerate() {
+            let (from, to) = (indicator[i], indicator[i + 3]);
+            let known = [(product, from, to), (inverse, to, from)];
+            if known.iter().any(|(p, a, b)| p.get(*a).is_some_and(|image| image != *b)) {
+                return Err(AnalysisError::InconsistentIndicator(n));
+            }
+            for (p, a, b) in known {
+                p.0[usize::from(a)] = Some(b);
+            }
+        }
+    }
+    Ok(products)
+}
+
+/// Cycle lengths of `AD`, `BE` and `CF`, longest first
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Characteristic(pub [Vec<u8>; 3]);
+
+impl Characteristic {
+    /// Characteristic of complete products, `None` if one is incomplete
+    pub fn new(products: &[Product; 3]) -> Option<Self> {
+        let [ad, be, cf] = products;
+        Some(Self([lengths(ad)?, lengths(be)?, lengths(cf)?]))
+    }
+
+    /// Characteristic of the rotors at `positions`, rings at `A` and no plugs
+    pub fn of_rotors(walzenlage: Walzenlage, positions: [EnigmaAlphabet; 3]) -> Self {
+        let mut enigma = machine(walzenlage, [0; 3], positions, Plugboard::default());
+        let permutations = scramblers(&mut enigma, 6);
+        let products: [Product; 3] = core::array::from_fn(|i| {
+            Product::from_permutations(&permutations[i], &permutations[i + 3])
+        });
+        Self::new(&products).expect("products of the machine are complete")
+    }
+}
+
+fn lengths(product: &Product) -> Option<Vec<u8>> {
+    Some(product.cycles()?.iter().map(|cycle| cycle.len() as u8).collect())
+}
+
+impl fmt::Display for Characteristic {
+    /// E.g. `13 13 | 10 10 3 3 | 9 9 4 4`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, lengths) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            for (j, length) in lengths.iter().enumerate() {
+                let separator = if j == 0 { "" } else { " " };
+                write!(f, "{separator}{length}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A wheel order and start position with a given characteristic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub walzenlage: Walzenlage,
+    /// Window letters at the Grundstellung with all rings at `A`, which are
+    /// the rotor cores of the true key
+    pub positions: [EnigmaAlphabet; 3],
+}
+
+impl Entry {
+    /// The rotor part of the daily key, rings at `01` and no plugs
+    pub fn daily_key(&self) -> DailyKey {
+        DailyKey {
+            walzenlage: self.walzenlage,
+            ringstellung: Ringstellung([0; 3]),
+            steckerverbindungen: Steckerverbindungen::default(),
+            grundstellung: Some(Grundstellung(self.positions)),
+        }
+    }
+}
+
+/// Characteristics of every start position of some wheel orders
+#[derive(Debug, Clone, Default)]
+pub struct Catalogue {
+    entries: HashMap<Characteristic, Vec<Entry>>,
+}
+
+impl Catalogue {
+    pub fn new(wheel_orders: impl IntoIterator<Item = Walzenlage>) -> Self {
+        let mut entries: HashMap<Characteristic, Vec<Entry>> = HashMap::new();
+        for walzenlage in wheel_orders {
+            for position in 0..26 * 26 * 26 {
+                let positions = [position / 676, position / 26 % 26, position % 26]
+                    .map(|p| EnigmaAlphabet::new(p as u8));
+                let characteristic = Characteristic::of_rotors(walzenlage, positions);
+                entries.entry(characteristic).or_default().push(Entry { walzenlage, positions });
+            }
+        }
+        Self { entries }
+    }
+
+    /// Wheel orders and positions giving `characteristic`
+    pub fn lookup(&self, characteristic: &Characteristic) -> &[Entry] {
+        self.entries.get(characteristic).map_or(&[], Vec::as_slice)
+    }
+
+    /// Number of different characteristics
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every characteristic with its entries, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&Characteristic, &[Entry])> {
+        self.entries.iter().map(|(characteristic, entries)| (characteristic, entries.as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_day;
+    use crate::core::engine::Enigma;
+    use crate::historical_machines::model::Model;
+    use crate::key::MessageKey;
+    use crate::procedure::doubled_indicator;
+    use crate::random::OperatorHabit;
+    use crate::random::Rng;
+
+    /// Grundstellung chosen so that the middle rotor stays put during the
+    /// indicator, with the day's rings as with the rings at `A`
+    fn key() -> DailyKey {
+        test_day::key_at("ABL")
+    }
+
+    fn indicators(count: usize) -> Vec<String> {
+        let mut rng = Rng::new(1938);
+        (0..count)
+            .map(|_| {
+                let message_key: MessageKey = rng.message_key(OperatorHabit::Careful);
+                doubled_indicator::send(Model::EnigmaI, &key(), message_key, "X").unwrap().indicator
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_products_and_characteristic() {
+        let indicators = indicators(150);
+        let day = products(indicators.iter().map(String::as_str)).unwrap();
+        assert!(day.iter().all(Product::is_complete));
+        let characteristic = Characteristic::new(&day).unwrap();
+        // cycles of equal length come in pairs
+        for lengths in &characteristic.0 {
+            assert_eq!(lengths.iter().map(|&length| usize::from(length)).sum::<usize>(), 26);
+            for length in lengths {
+                assert_eq!(lengths.iter().filter(|&other| other == length).count() % 2, 0);
+            }
+        }
+
+        // the plugboard only relabels letters within the cycles
+        let cores = key().grundstellung.unwrap().0.map(|letter| letter.to_u8());
+        let rings = key().ringstellung.0;
+        let cores = core::array::from_fn(|i| EnigmaAlphabet::new((cores[i] + 26 - rings[i]) % 26));
+        assert_eq!(Characteristic::of_rotors(key().walzenlage, cores), characteristic);
+
+        let partial = products(indicators[..5].iter().map(String::as_str)).unwrap();
+        assert!(!partial[0].is_complete());
+        assert_eq!(Characteristic::new(&partial), None);
+        assert!(partial[0].to_string().contains('→'));
+        assert!(day[0].to_string().starts_with('('));
+    }
+
+    #[test]
+    fn test_invalid_indicators() {
+        assert_eq!(products(["ABCDEF", "ABC"]), Err(AnalysisError::InvalidIndicator(1)));
+        assert_eq!(products(["ABCDEF", "AXYZGH"]), Err(AnalysisError::InconsistentIndicator(1)));
+        assert_eq!(
+            products(["ABCDEF", "GHIJKL", "XYZDMN"]),
+            Err(AnalysisError::InconsistentIndicator(2))
+        );
+    }
+
+    #[test]
+    fn test_catalogue_lookup() {
+        let wheel_orders: Vec<Walzenlage> = ["B I II III", "B I III II", "B II I III"]
+            .iter()
+            .map(|walzenlage| walzenlage.parse().unwrap())
+            .collect();
+        let catalogue = Catalogue::new(wheel_orders);
+        assert!(catalogue.len() > 1000);
+        assert_eq!(catalogue.iter().map(|(_, entries)| entries.len()).sum::<usize>(), 3 * 17576);
+
+        let indicators = indicators(150);
+        let day = products(indicators.iter().map(String::as_str)).unwrap();
+        let characteristic = Characteristic::new(&day).unwrap();
+        let entries = catalogue.lookup(&characteristic);
+        assert!(entries.len() < 20, "{} entries for {characteristic}", entries.len());
+        let found = entries
+            .iter()
+            .find(|entry| entry.walzenlage == key().walzenlage)
+            .expect("the day's wheel order is among the entries");
+
+        // the found rotor cores with the day's rings and plugs read the keys
+        let mut daily_key = found.daily_key();
+        daily_key.ringstellung = key().ringstellung;
+        daily_key.steckerverbindungen = key().steckerverbindungen;
+        let mut positions = found.positions;
+        for (position, ring) in positions.iter_mut().zip(daily_key.ringstellung.0) {
+            *position += ring;
+        }
+        daily_key.grundstellung = Some(Grundstellung(positions));
+        assert_eq!(daily_key, key());
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &daily_key).unwrap();
+        let doubled: String =
+            indicators[0].chars().map(|letter| enigma.encode(letter).to_char()).collect();
+        assert_eq!(doubled[..3], doubled[3..]);
+    }
+}
@@ -0,0 +1,323 @@
+//! Checking machine
+//!
+//! A bombe stop gives a wheel order, the rotor cores at the start of the
+//! message and the steckers of a few letters. The checking machine takes it
+//! from there: going through the crib it works out the steckers of every letter
+//! reached from the known ones, and throws the stop away as soon as a letter
+//! would need two different partners.
+//!
+//! The bombe runs with all rings at `A`, but the ring of the right rotor decides
+//! when the middle rotor moves and the ring of the middle rotor when the left
+//! rotor moves. Where the true rings move the middle rotor at another letter
+//! within the crib, the steckers are deduced once for every way the rotors can
+//! step over it, the rings at `A` first. For the deductions that survive, the
+//! ring settings come from where the crib goes garbled with the rings at `A`,
+//! see [`rings`]. The ring of the left rotor never changes the
+//! stepping and is left at `A`.
+
+use core::fmt;
+
+use super::bombe::Stop;
+use super::letters;
+use super::machine;
+use super::menu::Menu;
+use super::rings;
+use super::rings::Evidence;
+use super::scramblers;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::parts::plugboard::Plugboard;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::key::Ringstellung;
+use crate::key::Steckerverbindungen;
+use crate::key::Walzenlage;
+
+/// A key to check: rotor cores as found by the bombe and the steckers known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub walzenlage: Walzenlage,
+    /// Window letters at the start of the message with all rings at `A`
+    pub positions: [EnigmaAlphabet; 3],
+    pub steckers: Vec<(EnigmaAlphabet, EnigmaAlphabet)>,
+}
+
+impl Candidate {
+    pub fn from_stop(walzenlage: Walzenlage, stop: &Stop) -> Self {
+        let mut steckers = stop.steckers.clone();
+        steckers.extend(stop.stecker);
+        Self { walzenlage, positions: stop.positions, steckers }
+    }
+}
+
+/// Why a candidate was thrown away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contradiction {
+    /// Letter that would need two partners
+    pub letter: EnigmaAlphabet,
+    pub partners: (EnigmaAlphabet, EnigmaAlphabet),
+    /// Offset of the crib pair where it showed up, `None` if the known
+    /// steckers already disagree
+    pub offset: Option<usize>,
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, second) = self.partners;
+        write!(
+            f,
+            "{} steckered to both {} and {}",
+            self.letter.to_char(),
+            first.to_char(),
+            second.to_char()
+        )?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A candidate that survived, completed to a key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Confirmed {
+    /// The key, with the Grundstellung the message starts at
+    pub key: DailyKey,
+    /// Letters whose stecker the crib does not reach, left unplugged
+    pub unknown: Vec<EnigmaAlphabet>,
+    pub plaintext: String,
+    /// Letters of the menu that the recovered rings predict right or wrong as
+    /// the decrypt with the rings at `A` has them, out of all menu letters
+    pub agreement: (usize, usize),
+    /// Ring settings of the middle and right rotor that step the rotors the
+    /// same way through the whole message
+    pub equivalent_rings: usize,
+}
+
+/// Steckers worked out so far, `None` where a letter is not known yet
+#[derive(Debug, Clone, Copy)]
+struct Steckers([Option<EnigmaAlphabet>; 26]);
+
+impl Steckers {
+    fn assign(
+        &mut self,
+        a: EnigmaAlphabet,
+        b: EnigmaAlphabet,
+        offset: Option<usize>,
+    ) -> Result<bool, Contradiction> {
+        for (letter, partner) in [(a, b), (b, a)] {
+            match self.0[usize::from(letter)] {
+                Some(known) if known != partner => {
+                    return Err(Contradiction { letter, partners: (known, partner), offset });
+                }
+                Some(_) => return Ok(false),
+                None => {}
+            }
+        }
+        self.0[usize::from(a)] = Some(b);
+        self.0[usize::from(b)] = Some(a);
+        Ok(true)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckingMachine {
+    ciphertext: Vec<EnigmaAlphabet>,
+    menu: Menu,
+}
+
+impl CheckingMachine {
+    /// Checking machine for a message and the menu of its crib, offsets
+    /// counted from the start of the message
+    pub fn new(ciphertext: &str, menu: &Menu) -> Result<Self, AnalysisError> {
+        let ciphertext = letters(ciphertext)?;
+        let last = menu.edges.iter().map(|edge| edge.offset).max();
+        match last {
+            None => Err(AnalysisError::EmptyMenu),
+            Some(last) if last >= ciphertext.len() => Err(AnalysisError::TooShort),
+            Some(_) => Ok(Self { ciphertext, menu: menu.clone() }),
+        }
+    }
+
+    /// Complete `candidate` to a key, or report the contradiction with the
+    /// rings at `A` if no stepping through the crib avoids one
+    pub fn check(&self, candidate: &Candidate) -> Result<Confirmed, Contradiction> {
+        let assumed = DailyKey {
+            walzenlage: candidate.walzenlage,
+            ringstellung: Ringstellung([0; 3]),
+            steckerverbindungen: Steckerverbindungen::default(),
+            grundstellung: Some(Grundstellung(candidate.positions)),
+        };
+        let with_rings =
+            |middle, right| DailyKey { ringstellung: Ringstellung([0, middle, right]), ..assumed };
+        let crib = self.last_offset() + 1;
+
+        let mut steppings = Vec::new();
+        let mut contradiction = None;
+        let mut best: Option<Confirmed> = None;
+        for (middle, right) in (0..26).flat_map(|middle| (0..26).map(move |right| (middle, right)))
+        {
+            let stepping = rings::cores(&with_rings(middle, right), candidate.positions, crib);
+            if steppings.contains(&stepping) {
+                continue;
+            }
+            steppings.push(stepping);
+            let steckers = match self.deduce(candidate, [0, middle, right]) {
+                Ok(steckers) => steckers,
+                Err(e) => {
+                    contradiction.get_or_insert(e);
+                    continue;
+                }
+            };
+            let confirmed = self.confirm(&assumed, &steckers);
+            if best.as_ref().is_none_or(|best| confirmed.agreement.0 > best.agreement.0) {
+                best = Some(confirmed);
+            }
+        }
+
+        let mut best = best.ok_or_else(|| contradiction.expect("a stepping was rejected"))?;
+        let [_, middle, right] = best.key.ringstellung.0;
+        let len = self.ciphertext.len();
+        let stepping = rings::cores(&with_rings(middle, right), candidate.positions, len);
+        best.equivalent_rings = (0..26)
+            .flat_map(|middle| (0..26).map(move |right| (middle, right)))
+            .filter(|&(middle, right)| {
+                rings::cores(&with_rings(middle, right), candidate.positions, len) == stepping
+            })
+            .count();
+        Ok(best)
+    }
+
+    fn last_offset(&self) -> usize {
+        self.menu.edges.iter().map(|edge| edge.offset).max().expect("menu is not empty")
+    }
+
+    /// Steckers following from the known ones through the crib
+    fn deduce(&self, candidate: &Candidate, rings: [u8; 3]) -> Result<Steckers, Contradiction> {
+        let mut steckers = Steckers([None; 26]);
+        for &(a, b) in &candidate.steckers {
+            steckers.assign(a, b, None)?;
+        }
+
+        let mut enigma =
+            machine(candidate.walzenlage, rings, candidate.positions, Plugboard::default());
+        let scramblers = scramblers(&mut enigma, self.last_offset() + 1);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for edge in &self.menu.edges {
+                let scrambler = &scramblers[edge.offset];
+                for (from, to) in [(edge.plain, edge.cipher), (edge.cipher, edge.plain)] {
+                    if let Some(partner) = steckers.0[usize::from(from)] {
+                        let implied = scrambler[usize::from(partner)];
+                        changed |= steckers.assign(to, implied, Some(edge.offset))?;
+                    }
+                }
+            }
+        }
+        Ok(steckers)
+    }
+
+    /// Key with the steckers deduced, its rings recovered from the letters
+    /// of the menu the rings at `A` decrypt wrong
+    fn confirm(&self, assumed: &DailyKey, steckers: &Steckers) -> Confirmed {
+        let pairs = (0..26).filter_map(|i| {
+            let letter = EnigmaAlphabet::new(i as u8);
+            let partner = steckers.0[i]?;
+            (letter.to_u8() < partner.to_u8()).then_some((letter, partner))
+        });
+        let steckerverbindungen =
+            Steckerverbindungen::from_pairs(pairs).expect("steckers are consistent");
+        let key = DailyKey { steckerverbindungen, ..*assumed };
+        let recovery =
+            rings::recover_rings_from_letters(&self.ciphertext, &key, Evidence::Menu(&self.menu))
+                .expect("the menu lies within the message");
+        Confirmed {
+            key: recovery.key,
+            unknown: (0..26)
+                .filter(|&i| steckers.0[i].is_none())
+                .map(|i| EnigmaAlphabet::new(i as u8))
+                .collect(),
+            plaintext: recovery.plaintext,
+            agreement: recovery.agreement,
+            equivalent_rings: 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::crib;
+    use crate::core::engine::Enigma;
+    use crate::historical_machines::model::Model;
+
+    const CIPHERTEXT: &str = "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY SIOZV EQMIK UBPMM YLKLT TDEIS \
+                              MDICA GYKUA CTCDO MOHWX MUUIA UBSTS LRNBZ SZWNR FXWFY SSXJZ VIJHI DISHP RKLKA YUPAD \
+                              TXQSP INQMA TLPIF SVKDA SCTAC DPBOP VHJK";
+    const PLAINTEXT: &str = "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAXNORDWESTLXSEBEZXSEBEZXUAFFLIEGERSTRASZERIQTUNGX\
+                             DUBROWKIXDUBROWKIXOPOTSCHKAXOPOTSCHKAXUMXEINSAQTDREINULLXUHRANGETRETENXANGRIFFXINFXRGTX";
+
+    fn machine() -> CheckingMachine {
+        let placement =
+            crib::place(CIPHERTEXT, "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAX").unwrap().remove(0);
+        assert_eq!(placement.offset, 0);
+        CheckingMachine::new(CIPHERTEXT, &Menu::from_placement(&placement)).unwrap()
+    }
+
+    fn candidate(steckers: &str) -> Candidate {
+        let steckers: Steckerverbindungen = steckers.parse().unwrap();
+        Candidate {
+            walzenlage: "B II IV V".parse().unwrap(),
+            // the cores of BLA with rings 02 21 12
+            positions: ['A', 'R', 'P'].map(EnigmaAlphabet::from),
+            steckers: steckers.pairs().collect(),
+        }
+    }
+
+    #[test]
+    fn test_check_operation_barbarossa() {
+        let confirmed = machine().check(&candidate("AV")).unwrap();
+        assert_eq!(confirmed.plaintext, PLAINTEXT);
+        // the right ring moves the middle rotor at the 26th letter
+        assert_eq!(confirmed.key.ringstellung.0[2], 11);
+        assert_eq!(confirmed.key.grundstellung.unwrap().0[2], 'A'.into());
+        assert_eq!(confirmed.agreement, (40, 40));
+        // the middle rotor moves seven times, missing its turnover with 19 of
+        // the 26 middle rings
+        assert_eq!(confirmed.equivalent_rings, 19);
+        // with the rings at `A` the middle rotor moves at the 11th letter, and
+        // the crib contradicts itself after that
+        let at_a = machine().deduce(&candidate("AV"), [0; 3]).unwrap_err();
+        assert_eq!(at_a.offset, Some(13));
+
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &confirmed.key).unwrap();
+        let decrypt: String = letters(CIPHERTEXT)
+            .unwrap()
+            .into_iter()
+            .map(|letter| enigma.encode(letter).to_char())
+            .collect();
+        assert_eq!(decrypt, PLAINTEXT);
+    }
+
+    #[test]
+    fn test_reject_contradiction() {
+        let contradiction = machine().check(&candidate("AB")).unwrap_err();
+        assert!(contradiction.offset.is_some());
+        assert!(contradiction.to_string().contains(" steckered to both "));
+
+        let contradiction = machine().check(&Candidate {
+            steckers: vec![('A'.into(), 'V'.into()), ('A'.into(), 'B'.into())],
+            ..candidate("")
+        });
+        assert_eq!(
+            contradiction,
+            Err(Contradiction {
+                letter: 'A'.into(),
+                partners: ('V'.into(), 'B'.into()),
+                offset: None
+            })
+        );
+    }
+}
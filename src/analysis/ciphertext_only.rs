@@ -0,0 +1,525 @@
+//! Ciphertext-only attack
+//!
+//! Gillogly's attack as refined by Weierud and Sullivan, for messages without
+//! a crib:
+//!
+//! 1. Decrypt without plugs at every start position of every wheel order,
+//!    rings at a fixed default, and keep the settings whose decrypt has the
+//!    highest index of coincidence. Letters that are not steckered come out
+//!    right, so the true setting reads a little less randomly than the rest.
+//! 2. Try every ring setting of the right rotor with the same rotor cores,
+//!    which moves the turnover of the middle rotor, and keep the best few by
+//!    index of coincidence again.
+//! 3. Hill-climb the plugboard of each kept setting, scoring the decrypt by
+//!    bigrams of German military text. The best settings get random restarts
+//!    and a last search over the middle and right rings with their plugs in
+//!    place.
+//!
+//! The index of coincidence says little about the right ring, and the climb
+//! only finds the plugs once the middle rotor turns over at the right letter.
+//! Short messages are therefore much easier to break when the rings are known,
+//! for instance from a captured key sheet, and the ring search is turned off.
+//!
+//! The search can be bounded in time and spread over threads. It is
+//! reproducible for a given seed as long as the time budget does not run out.
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use super::coincidence;
+use super::index_of_coincidence;
+use super::letters;
+use super::machine;
+use super::plugboard::PlugboardSearch;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::decoder::Decoder;
+use crate::core::encoder::Encoder;
+use crate::core::engine::Enigma;
+use crate::core::parts::plugboard::Plugboard;
+use crate::historical_machines::model::Model;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::key::Ringstellung;
+use crate::key::Steckerverbindungen;
+use crate::key::Walzenlage;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttackOptions {
+    pub model: Model,
+    /// Wheel orders to search, every one of the model when empty
+    pub wheel_orders: Vec<Walzenlage>,
+    /// Ring settings assumed while searching start positions
+    pub rings: [u8; 3],
+    /// Search the ring settings of the middle and right rotor, off when the
+    /// rings are known
+    pub search_rings: bool,
+    /// Most plug pairs a key may have, ten was the rule from 1939
+    pub cables: usize,
+    /// Settings kept after the first stage
+    pub candidates: usize,
+    /// Ring settings of the right rotor kept for each of them in the second
+    /// stage
+    pub right_rings: usize,
+    /// Settings that get random restarts of the plugboard search
+    pub finalists: usize,
+    pub restarts: usize,
+    /// Number of keys reported
+    pub results: usize,
+    /// Work stops being handed out once this much time has passed, and the
+    /// best keys found by then are reported
+    pub time_budget: Option<Duration>,
+    pub threads: usize,
+    pub seed: u64,
+}
+
+impl AttackOptions {
+    pub fn new(model: Model) -> Self {
+        Self {
+            model,
+            wheel_orders: Vec::new(),
+            rings: [0; 3],
+            search_rings: true,
+            cables: 10,
+            candidates: 2000,
+            right_rings: 1,
+            finalists: 10,
+            restarts: 30,
+            results: 5,
+            time_budget: None,
+            threads: std::thread::available_parallelism().map_or(1, usize::from),
+            seed: 0,
+        }
+    }
+}
+
+/// A key found by the attack and the decrypt it gives
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// The key with the Grundstellung the message starts at
+    pub key: DailyKey,
+    /// Bigram log-probability of the decrypt, higher is better
+    pub score: f64,
+    pub plaintext: String,
+}
+
+/// Rotor setting under test
+#[derive(Debug, Clone, Copy)]
+struct Setting {
+    walzenlage: Walzenlage,
+    rings: [u8; 3],
+    cores: [EnigmaAlphabet; 3],
+    plugs: Steckerverbindungen,
+    score: f64,
+}
+
+impl Setting {
+    fn plugboard_search(
+        &self,
+        ciphertext: &[EnigmaAlphabet],
+        cables: usize,
+    ) -> PlugboardSearch<'static> {
+        PlugboardSearch::from_letters(ciphertext, self.walzenlage, self.rings, self.cores)
+            .with_cables(cables)
+    }
+}
+
+/// Scrambler substitution at every position of the rotor cores of a wheel
+/// order and the position the rotors step on to, so that the first stage
+/// decrypts by table lookups instead of running a machine for every start
+struct Positions {
+    substitutions: Vec<[u8; 26]>,
+    next: Vec<u16>,
+}
+
+impl Positions {
+    fn new(walzenlage: Walzenlage, rings: [u8; 3]) -> Self {
+        let mut enigma =
+            machine(walzenlage, rings, [EnigmaAlphabet::new(0); 3], Plugboard::default());
+        let set = |enigma: &mut Enigma, cores: [EnigmaAlphabet; 3]| {
+            enigma.set_positions(core::array::from_fn(|i| cores[i] + rings[i]))
+        };
+
+        // the right rotor wired through at each of its cores, both ways
+        let mut right = walzenlage.rotors[2].rotor();
+        let [mut forward, mut backward] = [[[0; 26]; 26]; 2];
+        for core in 0..26u8 {
+            right.set(core);
+            for letter in 0..26u8 {
+                forward[usize::from(core)][usize::from(letter)] = right.encode(letter).to_u8();
+                backward[usize::from(core)][usize::from(letter)] = right.decode(letter).to_u8();
+            }
+        }
+
+        // with the left and middle rotor standing, the right rotor only
+        // conjugates what the rest of the machine does
+        let mut substitutions = vec![[0; 26]; 26 * 26 * 26];
+        for position in (0..26 * 26 * 26).step_by(26) {
+            set(&mut enigma, Self::cores(position));
+            let first = enigma.permutation();
+            let mut rest = [0; 26];
+            for letter in 0..26 {
+                rest[letter] = forward[0][first[backward[0][letter] as usize].to_u8() as usize];
+            }
+            for core in 0..26 {
+                let substitution = &mut substitutions[position + core];
+                for letter in 0..26 {
+                    substitution[letter] =
+                        backward[core][rest[forward[core][letter] as usize] as usize];
+                }
+            }
+        }
+
+        // the left rotor has no say in how the rotors step
+        let mut next = vec![0; 26 * 26 * 26];
+        for position in 0..26 * 26 {
+            set(&mut enigma, Self::cores(position));
+            enigma.step();
+            let [left, middle, right] =
+                core::array::from_fn(|i| usize::from((enigma.positions()[i] - rings[i]).to_u8()));
+            for core in 0..26 {
+                next[core * 676 + position] =
+                    ((core + left) % 26 * 676 + middle * 26 + right) as u16;
+            }
+        }
+        Self { substitutions, next }
+    }
+
+    /// Rotor cores at position `index`, left to right
+    fn cores(index: usize) -> [EnigmaAlphabet; 3] {
+        [index / 676, index / 26 % 26, index % 26].map(|core| EnigmaAlphabet::new(core as u8))
+    }
+
+    /// Index of coincidence of the decrypt without plugs from position `start`
+    fn plugless_ic(&self, ciphertext: &[u8], start: usize) -> f64 {
+        let mut counts = [0; 26];
+        let mut position = start;
+        for &letter in ciphertext {
+            position = self.next[position] as usize;
+            counts[self.substitutions[position][letter as usize] as usize] += 1;
+        }
+        coincidence(&counts)
+    }
+}
+
+/// Index of coincidence of the decrypt without plugs
+fn plugless_ic(ciphertext: &[EnigmaAlphabet], setting: &Setting) -> f64 {
+    let mut enigma =
+        machine(setting.walzenlage, setting.rings, setting.cores, Plugboard::default());
+    let decrypt: Vec<_> = ciphertext.iter().map(|&letter| enigma.encode(letter)).collect();
+    index_of_coincidence(&decrypt)
+}
+
+/// Run `work` for every index below `count` on `threads` threads while the
+/// deadline has not passed, collecting the results. Indices are handed out in
+/// order, so the results are those of the first indices.
+fn parallel<T: Send>(
+    count: usize,
+    threads: usize,
+    deadline: Option<Instant>,
+    work: impl Fn(usize) -> T + Sync,
+) -> Vec<T> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for thread in 0..threads.clamp(1, count.max(1)) {
+            std::thread::Builder::new()
+                .name(format!("attack-{thread}"))
+                .spawn_scoped(scope, || loop {
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return;
+                    }
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= count {
+                        return;
+                    }
+                    let result = work(i);
+                    results
+                        .lock()
+                        .expect("no thread panics while holding the lock")
+                        .push((i, result));
+                })
+                .expect("failed to spawn an attack thread");
+        }
+    });
+    let mut results = results.into_inner().expect("no thread panicked");
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn by_score(settings: &mut [Setting]) {
+    settings.sort_by(|a, b| b.score.total_cmp(&a.score));
+}
+
+/// Search for the key of `ciphertext`, best solutions first
+pub fn attack(ciphertext: &str, options: &AttackOptions) -> Result<Vec<Solution>, AnalysisError> {
+    let deadline = options.time_budget.map(|budget| Instant::now() + budget);
+    let letters = letters(ciphertext)?;
+    if letters.len() < 2 {
+        return Err(AnalysisError::TooShort);
+    }
+    let wheel_orders = match options.wheel_orders.is_empty() {
+        true => Walzenlage::all(options.model).collect(),
+        false => options.wheel_orders.clone(),
+    };
+    for walzenlage in &wheel_orders {
+        walzenlage.check(options.model)?;
+    }
+
+    // stage 1: start positions without plugs
+    let ciphertext: Vec<u8> = letters.iter().map(|letter| letter.to_u8()).collect();
+    let found = parallel(wheel_orders.len(), options.threads, deadline, |i| {
+        let positions = Positions::new(wheel_orders[i], options.rings);
+        let scores: Vec<f64> = (0..26 * 26 * 26)
+            .map(|position| positions.plugless_ic(&ciphertext, position))
+            .collect();
+        let mut kept: Vec<usize> = (0..scores.len()).collect();
+        if options.candidates < kept.len() {
+            kept.select_nth_unstable_by(options.candidates, |&a, &b| {
+                scores[b].total_cmp(&scores[a])
+            });
+            kept.truncate(options.candidates);
+        }
+        kept.into_iter()
+            .map(|position| Setting {
+                walzenlage: wheel_orders[i],
+                rings: options.rings,
+                cores: Positions::cores(position),
+                plugs: Steckerverbindungen::default(),
+                score: scores[position],
+            })
+            .collect::<Vec<_>>()
+    });
+    let mut settings: Vec<Setting> = found.into_iter().flatten().collect();
+    by_score(&mut settings);
+    settings.truncate(options.candidates);
+
+    // stage 2: ring of the right rotor
+    if options.search_rings {
+        let found = parallel(settings.len(), options.threads, deadline, |i| {
+            let mut rings: Vec<Setting> = (0..26)
+                .map(|ring| {
+                    let mut setting = settings[i];
+                    setting.rings[2] = ring;
+                    Setting { score: plugless_ic(&letters, &setting), ..setting }
+                })
+                .collect();
+            by_score(&mut rings);
+            rings.truncate(options.right_rings.max(1));
+            rings
+        });
+        // settings the deadline left out keep their ring
+        let rest = settings.split_off(found.len());
+        settings = found.into_iter().flatten().chain(rest).collect();
+    }
+
+    // stage 3: plugboard, scoring settings the deadline left out without plugs
+    // when too few were climbed to report
+    let mut climbed = parallel(settings.len(), options.threads, deadline, |i| {
+        let found = settings[i]
+            .plugboard_search(&letters, options.cables)
+            .run()
+            .expect("climb without fixed pairs");
+        Setting { plugs: found.steckerverbindungen, score: found.score, ..settings[i] }
+    });
+    let missing = options.results.saturating_sub(climbed.len());
+    climbed.extend(settings[climbed.len()..].iter().take(missing).map(|setting| Setting {
+        score: setting.plugboard_search(&letters, options.cables).score(&setting.plugs),
+        ..*setting
+    }));
+    let mut settings = climbed;
+    by_score(&mut settings);
+    settings.truncate(options.finalists.max(options.results));
+
+    // settings the deadline left out keep what the climb found
+    let refined = parallel(settings.len(), options.threads, deadline, |i| {
+        let found = settings[i]
+            .plugboard_search(&letters, options.cables)
+            .with_start(settings[i].plugs)
+            .with_restarts(options.restarts, options.seed ^ i as u64)
+            .run()
+            .expect("climb without fixed pairs");
+        let best = Setting { plugs: found.steckerverbindungen, score: found.score, ..settings[i] };
+        match options.search_rings {
+            true => refine_rings(&letters, options.cables, best),
+            false => best,
+        }
+    });
+    settings.splice(..refined.len(), refined);
+    by_score(&mut settings);
+    settings.truncate(options.results);
+
+    Ok(settings.into_iter().map(|setting| solution(&letters, setting)).collect())
+}
+
+/// Best ring settings of the middle and right rotor for `setting` with its
+/// plugs, keeping the rotor cores, followed by another plugboard climb
+fn refine_rings(ciphertext: &[EnigmaAlphabet], cables: usize, setting: Setting) -> Setting {
+    let mut best = setting;
+    for middle in 0..26 {
+        for right in 0..26 {
+            let mut candidate = setting;
+            candidate.rings[1] = middle;
+            candidate.rings[2] = right;
+            candidate.score =
+                candidate.plugboard_search(ciphertext, cables).score(&candidate.plugs);
+            if candidate.score > best.score {
+                best = candidate;
+            }
+        }
+    }
+    let found = best
+        .plugboard_search(ciphertext, cables)
+        .with_start(best.plugs)
+        .run()
+        .expect("climb without fixed pairs");
+    Setting { plugs: found.steckerverbindungen, score: found.score, ..best }
+}
+
+fn solution(ciphertext: &[EnigmaAlphabet], setting: Setting) -> Solution {
+    let mut enigma =
+        machine(setting.walzenlage, setting.rings, setting.cores, setting.plugs.into());
+    let grundstellung = Grundstellung(enigma.positions());
+    let plaintext = ciphertext.iter().map(|&letter| enigma.encode(letter).to_char()).collect();
+    Solution {
+        key: DailyKey {
+            walzenlage: setting.walzenlage,
+            ringstellung: Ringstellung(setting.rings),
+            steckerverbindungen: setting.plugs,
+            grundstellung: Some(grundstellung),
+        },
+        score: setting.score,
+        plaintext,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_sheet::generator;
+    use crate::key_sheet::generator::KeySheetOptions;
+    use crate::random::OperatorHabit;
+    use crate::random::Rng;
+
+    const CIPHERTEXT: &str = "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY SIOZV EQMIK UBPMM YLKLT TDEIS \
+                              MDICA GYKUA CTCDO MOHWX MUUIA UBSTS LRNBZ SZWNR FXWFY SSXJZ VIJHI DISHP RKLKA YUPAD \
+                              TXQSP INQMA TLPIF SVKDA SCTAC DPBOP VHJK";
+    const PLAINTEXT: &str = "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAXNORDWESTLXSEBEZXSEBEZXUAFFLIEGERSTRASZERIQTUNGX\
+                             DUBROWKIXDUBROWKIXOPOTSCHKAXOPOTSCHKAXUMXEINSAQTDREINULLXUHRANGETRETENXANGRIFFXINFXRGTX";
+
+    /// Message of a day from a generated 1938 sheet with six cables
+    fn generated_message() -> (DailyKey, String) {
+        let options = KeySheetOptions { cables: 6, ..KeySheetOptions::heer() };
+        let sheet = generator::generate(&options, 1938, 10, 1).unwrap();
+        let start = Rng::new(1).message_key(OperatorHabit::Careful);
+        let key = DailyKey { grundstellung: Some(Grundstellung(start.0)), ..sheet.days[0].key };
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &key).unwrap();
+        let ciphertext = letters(GENERATED_PLAINTEXT)
+            .unwrap()
+            .into_iter()
+            .map(|letter| enigma.encode(letter).to_char())
+            .collect();
+        (key, ciphertext)
+    }
+
+    const GENERATED_PLAINTEXT: &str = "AUFKLAERUNGXMELDETXFEINDLICHEXPANZERKOLONNEXAUFXDERXSTRASSEXNACHXWILNAX\
+                                       STAERKEXETWAXZWANZIGXFAHRZEUGEXEIGENEXVORHUTXHAELTXDIEXBRUECKEXBEIXKOWNOX\
+                                       ERBITTEXARTILLERIEUNTERSTUETZUNGXKOMMANDEURXZWEITEXPANZERDIVISION";
+
+    #[test]
+    fn test_generated_message_ciphertext_only() {
+        // every wheel order, the rings known from the captured sheet
+        let (key, ciphertext) = generated_message();
+        let options = AttackOptions {
+            rings: key.ringstellung.0,
+            search_rings: false,
+            cables: 6,
+            candidates: 100,
+            finalists: 3,
+            restarts: 3,
+            results: 3,
+            ..AttackOptions::new(Model::EnigmaI)
+        };
+        let solutions = attack(&ciphertext, &options).unwrap();
+        assert_eq!(solutions[0].plaintext, GENERATED_PLAINTEXT);
+        assert_eq!(solutions[0].key, key);
+        assert!(solutions.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --release --ignored --nocapture"]
+    fn bench_operation_barbarossa_ciphertext_only() {
+        // every wheel order, rings searched from `A`: the true cores rank far
+        // behind thousands of others by index of coincidence, so this measures
+        // how long the search takes and reports whether it got there
+        let options = AttackOptions {
+            candidates: 6000,
+            right_rings: 5,
+            time_budget: Some(Duration::from_secs(1800)),
+            ..AttackOptions::new(Model::EnigmaI)
+        };
+        let started = Instant::now();
+        let solutions = attack(CIPHERTEXT, &options).unwrap();
+        let best = &solutions[0];
+        eprintln!(
+            "{:.1?}, best {} scoring {:.1}, {}",
+            started.elapsed(),
+            best.key,
+            best.score,
+            match best.plaintext == PLAINTEXT {
+                true => "solved",
+                false => "not solved",
+            }
+        );
+
+        assert!(solutions.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &best.key).unwrap();
+        let decrypt: String = letters(CIPHERTEXT)
+            .unwrap()
+            .into_iter()
+            .map(|letter| enigma.encode(letter).to_char())
+            .collect();
+        assert_eq!(decrypt, best.plaintext);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let options =
+            AttackOptions { time_budget: Some(Duration::ZERO), ..AttackOptions::new(Model::M3) };
+        assert_eq!(attack(CIPHERTEXT, &options), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_position_tables_match_the_machine() {
+        let ciphertext = letters(CIPHERTEXT).unwrap();
+        let bytes: Vec<u8> = ciphertext.iter().map(|letter| letter.to_u8()).collect();
+        let walzenlage = "B II IV V".parse().unwrap();
+        let positions = Positions::new(walzenlage, [1, 20, 11]);
+        // the true cores and two others
+        for cores in ["ARP", "ADE", "ZZZ"] {
+            let cores = letters(cores).unwrap().try_into().unwrap();
+            let setting = Setting {
+                walzenlage,
+                rings: [1, 20, 11],
+                cores,
+                plugs: Steckerverbindungen::default(),
+                score: 0.0,
+            };
+            let index = cores.iter().fold(0, |index, &core| index * 26 + usize::from(core));
+            assert_eq!(Positions::cores(index), cores);
+            assert_eq!(positions.plugless_ic(&bytes, index), plugless_ic(&ciphertext, &setting));
+        }
+    }
+
+    #[test]
+    fn test_attack_errors() {
+        let options = AttackOptions::new(Model::M3);
+        assert_eq!(attack("A", &options), Err(AnalysisError::TooShort));
+        let options =
+            AttackOptions { wheel_orders: vec!["A I II III".parse().unwrap()], ..options };
+        assert!(matches!(attack(CIPHERTEXT, &options), Err(AnalysisError::Key(_))));
+    }
+}
@@ -0,0 +1,219 @@
+//! Crib placement
+//!
+//! Enigma never enciphers a letter to itself, so a probable word (crib) cannot
+//! sit at an offset where one of its letters lines up with the same letter of
+//! the ciphertext. Sliding the crib along the message leaves the offsets worth
+//! running on a bombe, each with the plaintext/ciphertext pairs it implies.
+//!
+//! ```text
+//! Offset | Crib | Loops | Alignment
+//! -------+------+-------+----------
+//!      6 |    0 |     0 | ABTEILUNG
+//!        |      |       | RGYSZRCXN
+//! ```
+
+use core::cmp::Reverse;
+use core::fmt;
+use core::ops::Range;
+
+use super::letters;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+
+/// A crib at one offset of the ciphertext where it does not crash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    /// Index of the crib in the search
+    pub crib: usize,
+    pub offset: usize,
+    /// Plaintext and ciphertext letter at each position of the crib
+    pub pairs: Vec<(EnigmaAlphabet, EnigmaAlphabet)>,
+    /// Closed loops in the letter graph of the pairs, the more the better a
+    /// bombe menu the placement gives
+    pub loops: usize,
+}
+
+impl Placement {
+    fn new(crib: usize, offset: usize, pairs: Vec<(EnigmaAlphabet, EnigmaAlphabet)>) -> Self {
+        let loops = LetterGraph::new(pairs.iter().copied()).loops();
+        Self { crib, offset, pairs, loops }
+    }
+
+    pub fn plaintext(&self) -> String {
+        self.pairs.iter().map(|(plain, _)| plain.to_char()).collect()
+    }
+
+    pub fn ciphertext(&self) -> String {
+        self.pairs.iter().map(|(_, cipher)| cipher.to_char()).collect()
+    }
+}
+
+/// The 26 letters joined by one edge per pair, as a union-find
+pub(super) struct LetterGraph {
+    parent: [usize; 26],
+    /// Closed loops in the component of each root
+    loops: [usize; 26],
+}
+
+impl LetterGraph {
+    pub(super) fn new(pairs: impl IntoIterator<Item = (EnigmaAlphabet, EnigmaAlphabet)>) -> Self {
+        let mut graph = Self { parent: core::array::from_fn(|i| i), loops: [0; 26] };
+        for (a, b) in pairs {
+            let (a, b) = (graph.root(a), graph.root(b));
+            if a == b {
+                graph.loops[a] += 1;
+            } else {
+                graph.parent[a] = b;
+                graph.loops[b] += graph.loops[a];
+            }
+        }
+        graph
+    }
+
+    /// Representative of the component holding `letter`
+    pub(super) fn root(&self, letter: EnigmaAlphabet) -> usize {
+        let mut node = usize::from(letter);
+        while self.parent[node] != node {
+            node = self.parent[node];
+        }
+        node
+    }
+
+    /// Cycle rank of the component holding `letter`
+    pub(super) fn loops_through(&self, letter: EnigmaAlphabet) -> usize {
+        self.loops[self.root(letter)]
+    }
+
+    /// Cycle rank of the whole graph
+    pub(super) fn loops(&self) -> usize {
+        (0..26).filter(|&i| self.parent[i] == i).map(|i| self.loops[i]).sum()
+    }
+}
+
+/// Every offset of `ciphertext` where `crib` does not crash, in order
+pub fn place(ciphertext: &str, crib: &str) -> Result<Vec<Placement>, AnalysisError> {
+    let mut placements = search(ciphertext, &[crib], 0..usize::MAX)?.0;
+    placements.sort_by_key(|placement| placement.offset);
+    Ok(placements)
+}
+
+/// Placements of several cribs that start within `window`, best first: most
+/// loops, then longest crib, then earliest offset
+pub fn search(
+    ciphertext: &str,
+    cribs: &[&str],
+    window: Range<usize>,
+) -> Result<AlignmentTable, AnalysisError> {
+    let ciphertext = letters(ciphertext)?;
+    let mut placements = Vec::new();
+    for (index, crib) in cribs.iter().enumerate() {
+        let crib = letters(crib)?;
+        if crib.is_empty() {
+            return Err(AnalysisError::EmptyCrib);
+        }
+        let last = ciphertext.len().saturating_sub(crib.len() - 1);
+        for offset in window.start..window.end.min(last) {
+            let cipher = &ciphertext[offset..offset + crib.len()];
+            if crib.iter().zip(cipher).all(|(plain, cipher)| plain != cipher) {
+                let pairs = crib.iter().copied().zip(cipher.iter().copied()).collect();
+                placements.push(Placement::new(index, offset, pairs));
+            }
+        }
+    }
+    placements.sort_by_key(|placement| {
+        (Reverse(placement.loops), Reverse(placement.pairs.len()), placement.offset)
+    });
+    Ok(AlignmentTable(placements))
+}
+
+/// Placements from [`search`], printed one crib over its ciphertext
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlignmentTable(pub Vec<Placement>);
+
+impl fmt::Display for AlignmentTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Offset | Crib | Loops | Alignment")?;
+        write!(f, "-------+------+-------+----------")?;
+        for placement in &self.0 {
+            write!(
+                f,
+                "\n{:>6} | {:>4} | {:>5} | {}",
+                placement.offset,
+                placement.crib,
+                placement.loops,
+                placement.plaintext()
+            )?;
+            write!(f, "\n{:>6} | {:>4} | {:>5} | {}", "", "", "", placement.ciphertext())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIPHERTEXT: &str = "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE";
+
+    #[test]
+    fn test_place_crib() {
+        let placements = place(CIPHERTEXT, "ABTEILUNG").unwrap();
+        let ciphertext = letters(CIPHERTEXT).unwrap();
+        let offsets: Vec<usize> = placements.iter().map(|placement| placement.offset).collect();
+
+        // the crib really sits at offset 6, after AUFKLX
+        assert!(offsets.contains(&6));
+        for offset in 0..=ciphertext.len() - 9 {
+            let crashes = "ABTEILUNG"
+                .chars()
+                .zip(&ciphertext[offset..])
+                .any(|(plain, &cipher)| cipher == EnigmaAlphabet::from(plain));
+            assert_eq!(offsets.contains(&offset), !crashes, "offset {offset}");
+        }
+
+        let placement = &placements[offsets.iter().position(|&offset| offset == 6).unwrap()];
+        assert_eq!(placement.plaintext(), "ABTEILUNG");
+        assert_eq!(placement.ciphertext(), "RGYSZRCXN");
+        assert_eq!(placement.pairs[0], ('A'.into(), 'R'.into()));
+    }
+
+    #[test]
+    fn test_search() {
+        let table = search(CIPHERTEXT, &["ABTEILUNG", "KURTINOWA"], 0..12).unwrap();
+        assert!(table.0.iter().all(|placement| placement.offset < 12));
+        assert!(table.0.iter().any(|placement| placement.crib == 1));
+        assert!(table.0.windows(2).all(|pair| pair[0].loops >= pair[1].loops));
+
+        let text = table.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Offset | Crib | Loops | Alignment");
+        assert_eq!(lines.len(), 2 + 2 * table.0.len());
+
+        assert_eq!(search(CIPHERTEXT, &[""], 0..10), Err(AnalysisError::EmptyCrib));
+        assert_eq!(place(CIPHERTEXT, "abc"), Err(AnalysisError::InvalidLetter('a')));
+        assert_eq!(place("ABC", "ABCDE"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_loops() {
+        let pairs = |text: &str| -> Vec<_> {
+            text.split(' ')
+                .map(|pair| {
+                    let pair = letters(pair).unwrap();
+                    (pair[0], pair[1])
+                })
+                .collect()
+        };
+        let loops = |text| LetterGraph::new(pairs(text)).loops();
+        assert_eq!(loops("AB BC CD"), 0);
+        assert_eq!(loops("AB BC CA"), 1);
+        assert_eq!(loops("AB AB BC CA DE"), 2);
+
+        let graph = LetterGraph::new(pairs("AB BC CA DE EF FD GH"));
+        assert_eq!(graph.loops_through('A'.into()), 1);
+        assert_eq!(graph.loops_through('F'.into()), 1);
+        assert_eq!(graph.loops_through('H'.into()), 0);
+        assert_eq!(graph.root('A'.into()), graph.root('C'.into()));
+        assert_ne!(graph.root('A'.into()), graph.root('D'.into()));
+    }
+}
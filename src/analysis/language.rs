@@ -0,0 +1,387 @@
+//! Language models
+//!
+//! Fitness functions telling how much a decrypt reads like plaintext, for the
+//! attacks that have no crib to go on. Every [`Scorer`] gives higher scores to
+//! text that is closer to the language:
+//!
+//! - [`IndexOfCoincidence`]: how often two letters of the text agree, blind to
+//!   which letters they are.
+//! - [`ChiSquared`]: distance of the letter counts from those of the language,
+//!   negated.
+//! - [`NgramTable`]: summed log-probability of every run of one to four
+//!   letters.
+//!
+//! A [`LanguageModel`] holds the uni-, bi-, tri- and quadgram counts of a
+//! corpus. As text it is one `NGRAM COUNT` line per n-gram seen, e.g. `EN 812`,
+//! and [`LanguageModel::german`] and [`LanguageModel::english`] are read from
+//! tables of that form shipped with the crate.
+//!
+//! The shipped tables are placeholders. They are counted from the samples next
+//! to them in `corpus/`, some 6000 letters each, the German one of military
+//! text written the way it was sent, see [`crate::conventions`]. That is
+//! plenty for letter and bigram frequencies, but most trigrams and quadgrams
+//! of the language never occur in it and only get the floor probability, so
+//! scores of those lengths are rough. For serious work count a corpus of
+//! several megabytes with [`LanguageModelBuilder::with_file`], keep the table
+//! the model prints and parse it back with [`str::parse`].
+
+use core::fmt;
+use core::str::FromStr;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::index_of_coincidence;
+use crate::core::alphabet::EnigmaAlphabet;
+
+/// Longest n-gram counted
+pub const MAX_N: usize = 4;
+
+/// Probability given to n-grams the corpus never shows, as a fraction of one
+/// occurrence
+const FLOOR: f64 = 0.01;
+
+/// Fitness of a candidate plaintext, higher is better
+pub trait Scorer {
+    fn score(&self, text: &[EnigmaAlphabet]) -> f64;
+}
+
+impl<S: Scorer + ?Sized> Scorer for &S {
+    fn score(&self, text: &[EnigmaAlphabet]) -> f64 {
+        (**self).score(text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexOfCoincidence;
+
+impl Scorer for IndexOfCoincidence {
+    fn score(&self, text: &[EnigmaAlphabet]) -> f64 {
+        index_of_coincidence(text)
+    }
+}
+
+/// Chi-squared statistic of the letter counts against the frequencies of a
+/// language, negated so that closer text scores higher
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiSquared {
+    frequencies: [f64; 26],
+}
+
+impl ChiSquared {
+    pub fn new(model: &LanguageModel) -> Self {
+        let total = model.total(1).max(1) as f64;
+        let frequencies =
+            core::array::from_fn(|i| f64::from(model.counts[0][i]).max(FLOOR) / total);
+        Self { frequencies }
+    }
+}
+
+impl Scorer for ChiSquared {
+    fn score(&self, text: &[EnigmaAlphabet]) -> f64 {
+        let mut counts = [0usize; 26];
+        for &letter in text {
+            counts[usize::from(letter)] += 1;
+        }
+        let n = text.len() as f64;
+        -counts
+            .iter()
+            .zip(self.frequencies)
+            .map(|(&count, frequency)| {
+                let expected = n * frequency;
+                (count as f64 - expected).powi(2) / expected
+            })
+            .sum::<f64>()
+    }
+}
+
+/// Natural log-probabilities of all n-grams of one length
+#[derive(Clone, PartialEq)]
+pub struct NgramTable {
+    n: usize,
+    log_probabilities: Vec<f32>,
+}
+
+impl NgramTable {
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Log-probability of `ngram`, which must have length [`n`](Self::n)
+    pub fn log_probability(&self, ngram: &[EnigmaAlphabet]) -> f64 {
+        assert_eq!(ngram.len(), self.n, "n-gram of the wrong length");
+        f64::from(self.log_probabilities[index(ngram)])
+    }
+}
+
+impl fmt::Debug for NgramTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NgramTable").field("n", &self.n).finish_non_exhaustive()
+    }
+}
+
+impl Scorer for NgramTable {
+    /// Sum over every n-gram of `text`, 0 for text shorter than one
+    fn score(&self, text: &[EnigmaAlphabet]) -> f64 {
+        text.windows(self.n).map(|ngram| f64::from(self.log_probabilities[index(ngram)])).sum()
+    }
+}
+
+fn index(ngram: &[EnigmaAlphabet]) -> usize {
+    ngram.iter().fold(0, |i, &letter| i * 26 + usize::from(letter))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageModelError {
+    /// Line `n` (counting from 1) is not an n-gram of one to four letters
+    /// followed by its count
+    InvalidLine(usize),
+}
+
+impl fmt::Display for LanguageModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LanguageModelError::InvalidLine(n) => {
+                write!(f, "line {n} is not an n-gram followed by its count")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LanguageModelError {}
+
+/// N-gram counts of a corpus and the tables taken from them
+#[derive(Clone, PartialEq)]
+pub struct LanguageModel {
+    /// Counts of the n-grams of length `n + 1`, indexed in base 26
+    counts: [Vec<u32>; MAX_N],
+    tables: [NgramTable; MAX_N],
+}
+
+impl LanguageModel {
+    fn from_counts(counts: [Vec<u32>; MAX_N]) -> Self {
+        let tables = core::array::from_fn(|i| {
+            let total = counts[i].iter().map(|&count| f64::from(count)).sum::<f64>().max(1.0);
+            let log_probabilities = counts[i]
+                .iter()
+                .map(|&count| (f64::from(count).max(FLOOR) / total).ln() as f32)
+                .collect();
+            NgramTable { n: i + 1, log_probabilities }
+        });
+        Self { counts, tables }
+    }
+
+    /// Model of German military text, counted from a placeholder sample, see
+    /// the [module documentation](self)
+    pub fn german() -> &'static Self {
+        static GERMAN: OnceLock<LanguageModel> = OnceLock::new();
+        GERMAN.get_or_init(|| include_str!("corpus/german.ngrams").parse().expect("valid table"))
+    }
+
+    /// Model of English text, counted from a placeholder sample, see the
+    /// [module documentation](self)
+    pub fn english() -> &'static Self {
+        static ENGLISH: OnceLock<LanguageModel> = OnceLock::new();
+        ENGLISH.get_or_init(|| include_str!("corpus/english.ngrams").parse().expect("valid table"))
+    }
+
+    /// Table of the n-grams of length `n`, from 1 to [`MAX_N`]
+    pub fn ngrams(&self, n: usize) -> &NgramTable {
+        assert!((1..=MAX_N).contains(&n), "n-grams of length {n} are not counted");
+        &self.tables[n - 1]
+    }
+
+    /// Times `ngram` was seen in the corpus
+    pub fn count(&self, ngram: &[EnigmaAlphabet]) -> u32 {
+        assert!(
+            (1..=MAX_N).contains(&ngram.len()),
+            "n-grams of length {} are not counted",
+            ngram.len()
+        );
+        self.counts[ngram.len() - 1][index(ngram)]
+    }
+
+    /// Number of n-grams of length `n` in the corpus
+    pub fn total(&self, n: usize) -> u64 {
+        self.counts[n - 1].iter().map(|&count| u64::from(count)).sum()
+    }
+}
+
+impl fmt::Debug for LanguageModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LanguageModel").field("letters", &self.total(1)).finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for LanguageModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, counts) in self.counts.iter().enumerate() {
+            for (index, &count) in counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+                let ngram: String = (0..=i)
+                    .rev()
+                    .map(|digit| {
+                        EnigmaAlphabet::new((index / 26usize.pow(digit as u32) % 26) as u8)
+                            .to_char()
+                    })
+                    .collect();
+                writeln!(f, "{ngram} {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LanguageModel {
+    type Err = LanguageModelError;
+
+    /// Blank lines are skipped and n-grams listed twice are added up
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut counts = empty_counts();
+        for (n, line) in s.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let invalid = LanguageModelError::InvalidLine(n + 1);
+            let mut fields = line.split_whitespace();
+            let (Some(ngram), Some(count), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid);
+            };
+            let count: u32 = count.parse().map_err(|_| invalid)?;
+            if !(1..=MAX_N).contains(&ngram.len()) || !ngram.bytes().all(|b| b.is_ascii_uppercase())
+            {
+                return Err(invalid);
+            }
+            let ngram: Vec<_> = ngram.bytes().map(EnigmaAlphabet::from_ascii).collect();
+            let entry = &mut counts[ngram.len() - 1][index(&ngram)];
+            *entry = entry.saturating_add(count);
+        }
+        Ok(LanguageModel::from_counts(counts))
+    }
+}
+
+fn empty_counts() -> [Vec<u32>; MAX_N] {
+    core::array::from_fn(|i| vec![0; 26usize.pow(i as u32 + 1)])
+}
+
+/// Counts n-grams of text into a [`LanguageModel`]
+///
+/// Only the letters A-Z count, in either case. Everything else is skipped, so
+/// n-grams run across spaces and punctuation but not from one text to the
+/// next. German text should go through [`Conventions`](crate::conventions)
+/// first to spell out numbers and umlauts the way operators did.
+#[derive(Clone)]
+pub struct LanguageModelBuilder {
+    counts: [Vec<u32>; MAX_N],
+}
+
+impl LanguageModelBuilder {
+    pub fn new() -> Self {
+        Self { counts: empty_counts() }
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        let letters: Vec<_> = text
+            .bytes()
+            .filter(u8::is_ascii_alphabetic)
+            .map(|b| EnigmaAlphabet::from_ascii(b.to_ascii_uppercase()))
+            .collect();
+        for (i, counts) in self.counts.iter_mut().enumerate() {
+            for ngram in letters.windows(i + 1) {
+                let entry = &mut counts[index(ngram)];
+                *entry = entry.saturating_add(1);
+            }
+        }
+        self
+    }
+
+    /// Count the text of a corpus file
+    pub fn with_file(self, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(self.with_text(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn build(self) -> LanguageModel {
+        LanguageModel::from_counts(self.counts)
+    }
+}
+
+impl Default for LanguageModelBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for LanguageModelBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LanguageModelBuilder").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conventions::Conventions;
+    use crate::conventions::Service;
+
+    fn letters(text: &str) -> Vec<EnigmaAlphabet> {
+        super::super::letters(text).unwrap()
+    }
+
+    #[test]
+    fn test_shipped_tables_match_corpus() {
+        let german =
+            Conventions::new(Service::Heer).encode(include_str!("corpus/german.txt")).unwrap();
+        let german = LanguageModelBuilder::new().with_text(&german).build();
+        assert_eq!(german.to_string(), include_str!("corpus/german.ngrams"));
+        assert!(german == *LanguageModel::german());
+
+        let english =
+            LanguageModelBuilder::new().with_text(include_str!("corpus/english.txt")).build();
+        assert_eq!(english.to_string(), include_str!("corpus/english.ngrams"));
+    }
+
+    #[test]
+    fn test_build_and_parse() {
+        let model = LanguageModelBuilder::new().with_text("Ab, ab!").with_text("c").build();
+        assert_eq!(model.to_string(), "A 2\nB 2\nC 1\nAB 2\nBA 1\nABA 1\nBAB 1\nABAB 1\n");
+        assert_eq!(model.count(&letters("BA")), 1);
+        assert_eq!(model.count(&letters("BC")), 0);
+        assert_eq!(model.total(2), 3);
+        assert!(model == model.to_string().parse().unwrap());
+
+        assert_eq!("A 2\nAB x".parse::<LanguageModel>(), Err(LanguageModelError::InvalidLine(2)));
+        assert_eq!("ABCDE 1".parse::<LanguageModel>(), Err(LanguageModelError::InvalidLine(1)));
+        assert_eq!("ab 1".parse::<LanguageModel>(), Err(LanguageModelError::InvalidLine(1)));
+        assert_eq!("\nA 1 2".parse::<LanguageModel>(), Err(LanguageModelError::InvalidLine(2)));
+    }
+
+    #[test]
+    fn test_scorers_prefer_language() {
+        let plain = letters("ANGRIFFXAUFXDENXBRUECKENKOPFXBEGINNTXUMXFUENFXUHRXDREISSIG");
+        let random = letters("QXZVJKWPYMFQTGZLHBVXCNDWKJQPMZRYXUVFGLTWQBHZDKMJXVCYPQNWZ");
+        let german = LanguageModel::german();
+        let scorers: [&dyn Scorer; 6] = [
+            &IndexOfCoincidence,
+            &ChiSquared::new(german),
+            german.ngrams(1),
+            german.ngrams(2),
+            german.ngrams(3),
+            german.ngrams(4),
+        ];
+        for scorer in scorers {
+            assert!(scorer.score(&plain) > scorer.score(&random));
+        }
+
+        let english = LanguageModel::english();
+        let text = letters("THEENEMYISADVANCINGALONGTHEROADTOTHENORTH");
+        assert!(english.ngrams(4).score(&text) > german.ngrams(4).score(&text));
+        assert!(german.ngrams(4).score(&plain) > english.ngrams(4).score(&plain));
+    }
+
+    #[test]
+    fn test_ngram_score() {
+        let model = LanguageModelBuilder::new().with_text("ABAB").build();
+        let table = model.ngrams(2);
+        // AB twice and BA once out of three bigrams
+        let expected = 2.0 * (2.0f64 / 3.0).ln() + (1.0f64 / 3.0).ln();
+        assert!((table.score(&letters("ABAB")) - expected).abs() < 1e-6);
+        assert_eq!(table.score(&letters("A")), 0.0);
+        assert!((table.log_probability(&letters("ZZ")) - (FLOOR / 3.0).ln()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,349 @@
+//! Bombe menus
+//!
+//! A crib aligned against the ciphertext gives pairs of letters, each enciphered
+//! into the other at a known offset. As a graph, with the letters as nodes and
+//! an edge per pair labelled with its offset, this is the menu wired into the
+//! bombe. Every closed loop lets the bombe reject about 25 of 26 wrong rotor
+//! positions, so a good menu has many loops, all in the component of the
+//! central letter.
+//!
+//! ```text
+//! Menu: 23 pairs, 22 letters, 3 loops, 2 components
+//! Central letter E, input letter C, 60 expected false stops over 60 wheel orders
+//!
+//!   A  13:O  19:K  20:D  22:G
+//!   B  16:J
+//!   D  11:R  20:A
+//!   E   1:F   4:G   8:R  10:O  12:S  15:X
+//!   ...
+//! ```
+
+use core::fmt;
+
+use super::bombe::Bombe;
+use super::bombe::MenuEdge;
+use super::crib::LetterGraph;
+use super::crib::Placement;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::key::Walzenlage;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Menu {
+    pub edges: Vec<MenuEdge>,
+}
+
+impl Menu {
+    pub fn new(edges: Vec<MenuEdge>) -> Self {
+        Self { edges }
+    }
+
+    /// Menu of a crib placement, offsets counted from the start of the message
+    pub fn from_placement(placement: &Placement) -> Self {
+        let edges = MenuEdge::from_pairs(&placement.pairs)
+            .into_iter()
+            .map(|edge| MenuEdge { offset: edge.offset + placement.offset, ..edge })
+            .collect();
+        Self { edges }
+    }
+
+    /// Letters of the menu in alphabetical order
+    pub fn letters(&self) -> Vec<EnigmaAlphabet> {
+        let mut seen = [false; 26];
+        for edge in &self.edges {
+            seen[usize::from(edge.plain)] = true;
+            seen[usize::from(edge.cipher)] = true;
+        }
+        (0..26).filter(|&i| seen[i]).map(|i| EnigmaAlphabet::new(i as u8)).collect()
+    }
+
+    /// Edge indices for every letter
+    fn links(&self) -> [Vec<usize>; 26] {
+        let mut links: [Vec<usize>; 26] = Default::default();
+        for (i, edge) in self.edges.iter().enumerate() {
+            links[usize::from(edge.plain)].push(i);
+            if edge.cipher != edge.plain {
+                links[usize::from(edge.cipher)].push(i);
+            }
+        }
+        links
+    }
+
+    fn graph(&self) -> LetterGraph {
+        LetterGraph::new(self.edges.iter().map(|edge| (edge.plain, edge.cipher)))
+    }
+
+    /// Number of connected pieces the menu falls into
+    pub fn components(&self) -> usize {
+        let graph = self.graph();
+        let mut roots: Vec<usize> =
+            self.letters().into_iter().map(|letter| graph.root(letter)).collect();
+        roots.sort_unstable();
+        roots.dedup();
+        roots.len()
+    }
+
+    /// Number of independent closed loops (the cycle rank) of the component
+    /// holding `letter`
+    pub fn loops_through_component(&self, letter: EnigmaAlphabet) -> usize {
+        self.graph().loops_through(letter)
+    }
+
+    /// Every closed loop of the menu as the indices of its edges, in the
+    /// order they are walked
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        let links = self.links();
+        let mut loops = Vec::new();
+        for (first, edge) in self.edges.iter().enumerate() {
+            // walk from the cipher letter back to the plain letter using only
+            // edges after `first`, so that every loop is found once
+            let mut walk = LoopWalk {
+                menu: self,
+                links: &links,
+                target: usize::from(edge.plain),
+                first,
+                path: vec![first],
+                visited: [false; 26],
+                loops: &mut loops,
+            };
+            walk.visited[usize::from(edge.cipher)] = true;
+            walk.walk(usize::from(edge.cipher));
+        }
+        loops
+    }
+
+    /// Letter for the test register: the one with the most independent loops
+    /// in its component, then the most pairs, then the first in the alphabet
+    pub fn central_letter(&self) -> Option<EnigmaAlphabet> {
+        let links = self.links();
+        self.letters().into_iter().max_by_key(|&letter| {
+            (
+                self.loops_through_component(letter),
+                links[usize::from(letter)].len(),
+                26 - letter.to_u8(),
+            )
+        })
+    }
+
+    /// Wire of the test register to put current on: a letter outside the
+    /// menu, as the central letter is unlikely to be steckered to it and a
+    /// right stop then shows a single dead wire naming the stecker
+    pub fn input_letter(&self) -> Option<EnigmaAlphabet> {
+        let central = self.central_letter()?;
+        let links = self.links();
+        (0..26)
+            .map(|i| EnigmaAlphabet::new(i as u8))
+            .filter(|&letter| letter != central)
+            .min_by_key(|&letter| (links[usize::from(letter)].len(), letter.to_u8()))
+    }
+
+    /// Turing's estimate of the stops at wrong positions: each of 17576
+    /// positions survives every independent loop with a chance of 1 in 26
+    pub fn expected_false_stops(&self, wheel_orders: usize) -> f64 {
+        let loops = self.central_letter().map_or(0, |letter| self.loops_through_component(letter));
+        wheel_orders as f64 * 17576.0 / 26f64.powi(loops as i32)
+    }
+
+    pub fn rate(&self, wheel_orders: usize) -> Result<Rating, AnalysisError> {
+        let central = self.central_letter().ok_or(AnalysisError::EmptyMenu)?;
+        Ok(Rating {
+            pairs: self.edges.len(),
+            letters: self.letters().len(),
+            loops: self.loops_through_component(central),
+            components: self.components(),
+            central,
+            input: self.input_letter().expect("menu has a central letter"),
+            wheel_orders,
+            expected_false_stops: self.expected_false_stops(wheel_orders),
+        })
+    }
+
+    /// Bombe for `walzenlage` with the test register on the central letter
+    pub fn bombe(&self, walzenlage: Walzenlage) -> Result<Bombe, AnalysisError> {
+        let rating = self.rate(1)?;
+        Ok(Bombe::new(walzenlage, self.edges.clone())?
+            .with_test_register(rating.central, rating.input))
+    }
+
+    /// Graphviz rendering, the central letter drawn with a double circle
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph menu {\n");
+        if let Some(central) = self.central_letter() {
+            dot.push_str(&format!("  {} [shape=doublecircle];\n", central.to_char()));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "  {} -- {} [label=\"{}\"];\n",
+                edge.plain.to_char(),
+                edge.cipher.to_char(),
+                edge.offset
+            ));
+        }
+        dot.push('}');
+        dot
+    }
+}
+
+/// Depth-first search for the loops that close with edge `first`
+struct LoopWalk<'a> {
+    menu: &'a Menu,
+    links: &'a [Vec<usize>; 26],
+    target: usize,
+    first: usize,
+    path: Vec<usize>,
+    visited: [bool; 26],
+    loops: &'a mut Vec<Vec<usize>>,
+}
+
+impl LoopWalk<'_> {
+    fn walk(&mut self, letter: usize) {
+        if letter == self.target {
+            self.loops.push(self.path.clone());
+            return;
+        }
+        let (links, first) = (self.links, self.first);
+        for &edge in links[letter].iter().filter(|&&edge| edge > first) {
+            let MenuEdge { plain, cipher, .. } = self.menu.edges[edge];
+            let next =
+                if usize::from(plain) == letter { usize::from(cipher) } else { usize::from(plain) };
+            if self.visited[next] && next != self.target {
+                continue;
+            }
+            self.visited[next] = true;
+            self.path.push(edge);
+            self.walk(next);
+            self.path.pop();
+            self.visited[next] = false;
+        }
+    }
+}
+
+/// Display lists every letter with the `offset:letter` pairs it takes part in
+impl fmt::Display for Menu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let links = self.links();
+        let mut first = true;
+        for letter in self.letters() {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(f, "  {}", letter.to_char())?;
+            for &edge in &links[usize::from(letter)] {
+                let MenuEdge { offset, plain, cipher } = self.edges[edge];
+                let other = if plain == letter { cipher } else { plain };
+                write!(f, " {offset:>3}:{}", other.to_char())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How good a menu is, see [`Menu::rate`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub pairs: usize,
+    pub letters: usize,
+    /// Independent closed loops in the component of the central letter
+    pub loops: usize,
+    pub components: usize,
+    pub central: EnigmaAlphabet,
+    pub input: EnigmaAlphabet,
+    pub wheel_orders: usize,
+    pub expected_false_stops: f64,
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Menu: {} pairs, {} letters, {} loops, {} components",
+            self.pairs, self.letters, self.loops, self.components
+        )?;
+        write!(
+            f,
+            "Central letter {}, input letter {}, {:.0} expected false stops over {} wheel orders",
+            self.central.to_char(),
+            self.input.to_char(),
+            self.expected_false_stops,
+            self.wheel_orders
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::crib;
+    use crate::analysis::letters;
+    use crate::core::engine::Enigma;
+    use crate::historical_machines::model::Model;
+    use crate::key::DailyKey;
+
+    fn menu(pairs: &str) -> Menu {
+        let pairs: Vec<_> = pairs
+            .split(' ')
+            .map(|pair| {
+                let pair = letters(pair).unwrap();
+                (pair[0], pair[1])
+            })
+            .collect();
+        Menu::new(MenuEdge::from_pairs(&pairs))
+    }
+
+    #[test]
+    fn test_loops() {
+        // A-B-C-A and A-C-D-A share the edge A-C
+        let menu = menu("AB BC CA CD DA EF");
+        assert_eq!(menu.components(), 2);
+        assert_eq!(menu.loops_through_component('A'.into()), 2);
+        let mut loops = menu.loops();
+        loops.iter_mut().for_each(|lp| lp.sort());
+        loops.sort();
+        assert_eq!(loops, [vec![0, 1, 2], vec![0, 1, 3, 4], vec![2, 3, 4]]);
+
+        assert_eq!(menu.central_letter(), Some('A'.into()));
+        assert_eq!(menu.input_letter(), Some('G'.into()));
+        assert_eq!(menu.expected_false_stops(1), 17576.0 / 676.0);
+    }
+
+    #[test]
+    fn test_rating_and_rendering() {
+        let placements = crib::place("EDPUD NRGYS ZRCXN", "ABTEILUNG").unwrap();
+        let placement = placements.iter().find(|placement| placement.offset == 6).unwrap();
+        let menu = Menu::from_placement(placement);
+        assert_eq!(menu.edges[0], MenuEdge { offset: 6, plain: 'A'.into(), cipher: 'R'.into() });
+
+        let rating = menu.rate(60).unwrap();
+        assert_eq!((rating.pairs, rating.loops), (9, 0));
+        assert_eq!(rating.expected_false_stops, 60.0 * 17576.0);
+        assert!(rating.to_string().starts_with("Menu: 9 pairs, 15 letters, 0 loops, 6 components"));
+
+        let dot = menu.to_dot();
+        assert!(dot.starts_with("graph menu {\n"));
+        assert!(dot.contains("  A -- R [label=\"6\"];\n"));
+        assert_eq!(menu.to_string().lines().count(), 15);
+        assert_eq!(Menu::new(Vec::new()).rate(1), Err(AnalysisError::EmptyMenu));
+    }
+
+    #[test]
+    fn test_menu_bombe() {
+        let key: DailyKey = "B II IV V 01 01 01 AV BS CG DL FU HZ IN KM OW RX BLW".parse().unwrap();
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &key).unwrap();
+        let crib = letters("WETTERVORHERSAGEBISKAYA").unwrap();
+        let pairs: Vec<_> = crib.into_iter().map(|plain| (plain, enigma.encode(plain))).collect();
+        let menu = Menu::new(MenuEdge::from_pairs(&pairs));
+        let rating = menu.rate(60).unwrap();
+        assert_eq!((rating.loops, rating.components), (3, 2));
+        assert_eq!((rating.central, rating.input), ('E'.into(), 'C'.into()));
+        assert_eq!(
+            menu.to_string().lines().nth(3),
+            Some("  E   1:F   4:G   8:R  10:O  12:S  15:X")
+        );
+
+        let bombe = menu.bombe(key.walzenlage).unwrap();
+        let stop = bombe.test(['B', 'L', 'W'].map(EnigmaAlphabet::from)).unwrap();
+        assert_eq!(stop.stecker, Some(('E'.into(), 'E'.into())));
+        assert!(stop.steckers.contains(&('A'.into(), 'V'.into())));
+    }
+}
@@ -0,0 +1,173 @@
+//! # Cryptanalysis
+//!
+//! Tools for attacking Enigma traffic, from the first step of every
+//! known-plaintext attack onwards.
+//!
+//! - [`ciphertext_only`]: recover a key from ciphertext alone by index of
+//!   coincidence and plugboard hill climbing.
+//! - [`crib`]: slide a probable word along the ciphertext and keep the
+//!   offsets where no letter would encipher to itself.
+//! - [`bombe`]: the Turing–Welchman Bombe, testing a menu at every rotor
+//!   position of a wheel order.
+//! - [`checking`]: confirm a bombe stop, complete its steckers and recover
+//!   the ring settings.
+//! - [`menu`]: build the bombe menu of a crib placement, find its loops and
+//!   rate it.
+//! - [`plugboard`]: recover the plug pairs of a known rotor setting by hill
+//!   climbing or simulated annealing.
+//! - [`rings`]: recover the ring settings of a key found with the rings at
+//!   `A` from where its decrypt goes garbled.
+//! - [`characteristic`]: Rejewski's cycle structure of a day's doubled
+//!   indicators and the catalogue to look it up in.
+//! - [`zygalski`]: Zygalski's perforated sheets, stacked over the females of
+//!   a day's indicators to find the wheel order and ring setting.
+//! - [`bomba`]: the Polish bomba, driving pairs of machines over every
+//!   position until three females of the same letter recur together.
+//! - [`banburismus`]: Turing's overlays of messages in depth scored in
+//!   decibans, and the Scritchmus chaining their distances.
+//! - [`language`]: n-gram models of German and English and the fitness
+//!   functions scoring a decrypt against them.
+
+use core::fmt;
+
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::engine::Enigma;
+use crate::core::parts::plugboard::Plugboard;
+use crate::historical_machines::model::RotorId;
+use crate::key::KeyError;
+use crate::key::Walzenlage;
+
+pub mod banburismus;
+pub mod bomba;
+pub mod bombe;
+pub mod characteristic;
+pub mod checking;
+pub mod ciphertext_only;
+pub mod crib;
+pub mod language;
+pub mod menu;
+pub mod plugboard;
+pub mod rings;
+#[cfg(test)]
+mod test_day;
+pub mod zygalski;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalysisError {
+    /// Text contains something other than whitespace and `A`-`Z`
+    InvalidLetter(char),
+    EmptyCrib,
+    /// A menu without any letter pairs
+    EmptyMenu,
+    /// Too little text to score
+    TooShort,
+    /// A key without the rotor positions the message starts at
+    MissingGrundstellung,
+    /// Indicator number `n` (counting from 0) is not six letters
+    InvalidIndicator(usize),
+    /// Indicator number `n` contradicts the ones before it
+    InconsistentIndicator(usize),
+    /// Distance number `n` contradicts the ones before it
+    InconsistentDistance(usize),
+    /// Indicator number `n` has no female where the search needs one
+    NoFemale(usize),
+    /// Annealing temperatures that are not positive
+    InvalidSchedule,
+    /// `n` fixed plug pairs, more than the cables allowed
+    TooManyFixedPairs(usize),
+    Key(KeyError),
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::InvalidLetter(ch) => write!(f, "{ch:?} is not a letter A-Z"),
+            AnalysisError::EmptyCrib => write!(f, "crib is empty"),
+            AnalysisError::EmptyMenu => write!(f, "menu has no letter pairs"),
+            AnalysisError::TooShort => write!(f, "text is too short"),
+            AnalysisError::MissingGrundstellung => write!(f, "key has no Grundstellung"),
+            AnalysisError::InvalidIndicator(n) => write!(f, "indicator {n} is not six letters"),
+            AnalysisError::InconsistentIndicator(n) => {
+                write!(f, "indicator {n} contradicts the ones before it")
+            }
+            AnalysisError::InconsistentDistance(n) => {
+                write!(f, "distance {n} contradicts the ones before it")
+            }
+            AnalysisError::NoFemale(n) => write!(f, "indicator {n} has no matching female"),
+            AnalysisError::InvalidSchedule => write!(f, "annealing temperatures must be positive"),
+            AnalysisError::TooManyFixedPairs(n) => {
+                write!(f, "{n} fixed plug pairs need more cables than allowed")
+            }
+            AnalysisError::Key(e) => write!(f, "invalid key: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AnalysisError {}
+
+impl From<KeyError> for AnalysisError {
+    fn from(value: KeyError) -> Self {
+        AnalysisError::Key(value)
+    }
+}
+
+/// Letters of `text`, skipping whitespace so grouped text can be used as is
+pub(crate) fn letters(text: &str) -> Result<Vec<EnigmaAlphabet>, AnalysisError> {
+    text.chars()
+        .filter(|ch| !ch.is_whitespace())
+        .map(|ch| match ch {
+            'A'..='Z' => Ok(ch.into()),
+            _ => Err(AnalysisError::InvalidLetter(ch)),
+        })
+        .collect()
+}
+
+/// Chance that two letters picked from `text` are the same, about 0.038 for
+/// random letters and 0.076 for German
+pub fn index_of_coincidence(text: &[EnigmaAlphabet]) -> f64 {
+    let mut counts = [0usize; 26];
+    for &letter in text {
+        counts[usize::from(letter)] += 1;
+    }
+    coincidence(&counts)
+}
+
+/// Index of coincidence of a text with `counts` of each letter
+pub(crate) fn coincidence(counts: &[usize; 26]) -> f64 {
+    let pairs: usize = counts.iter().map(|&count| count * count.saturating_sub(1)).sum();
+    let n: usize = counts.iter().sum();
+    if n < 2 {
+        return 0.0;
+    }
+    pairs as f64 / (n * (n - 1)) as f64
+}
+
+/// Machine for `walzenlage` whose rotor cores stand at `cores`: with a ring
+/// setting the window shows the core letter moved on by the ring
+pub(crate) fn machine(
+    walzenlage: Walzenlage,
+    rings: [u8; 3],
+    cores: [EnigmaAlphabet; 3],
+    plugboard: Plugboard,
+) -> Enigma {
+    let Walzenlage { reflector, rotors } = walzenlage;
+    let mut enigma = Enigma::new(reflector.reflector(), rotors.map(RotorId::rotor), plugboard);
+    enigma.set_rings(rings);
+    let mut positions = cores;
+    for (position, ring) in positions.iter_mut().zip(rings) {
+        *position += ring;
+    }
+    enigma.set_positions(positions);
+    enigma
+}
+
+/// Substitution of the scrambler for each of the next `len` letters typed on
+/// `enigma`
+pub(crate) fn scramblers(enigma: &mut Enigma, len: usize) -> Vec<[EnigmaAlphabet; 26]> {
+    (0..len)
+        .map(|_| {
+            enigma.step();
+            enigma.permutation()
+        })
+        .collect()
+}
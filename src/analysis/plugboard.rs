@@ -0,0 +1,420 @@
+//! Plugboard recovery
+//!
+//! With the wheel order, rings and rotor positions known, the plug pairs are
+//! all that is left: about 150 trillion ways to plug ten cables. The search
+//! changes the plugboard one move at a time and keeps what a [`Scorer`] likes
+//! about the decrypt:
+//!
+//! - add: plug two free letters together,
+//! - remove: unplug a pair,
+//! - swap: exchange partners between two pairs, or move one end of a pair to a
+//!   free letter.
+//!
+//! Hill climbing takes the best move until none improves the score. Simulated
+//! annealing takes random moves, worse ones too with a chance that shrinks as
+//! the temperature falls, and ends with a climb. The first run starts from the
+//! pairs already known, restarts add random pairs to them. Pairs fixed by the
+//! caller are never moved.
+
+use super::language::LanguageModel;
+use super::language::Scorer;
+use super::letters;
+use super::machine;
+use super::scramblers;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::parts::plugboard::Plugboard;
+use crate::key::Steckerverbindungen;
+use crate::key::Walzenlage;
+use crate::random::Rng;
+
+/// How the plugboard is searched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    /// Steepest ascent
+    HillClimb,
+    /// Temperature falling geometrically from `start` to `end` over `rounds`,
+    /// each trying a move for every pair of letters once. Temperatures are in
+    /// units of the score.
+    Annealing { start: f64, end: f64, rounds: usize },
+}
+
+/// Best plugboard found and how the search got there
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlugboardSolution {
+    pub steckerverbindungen: Steckerverbindungen,
+    pub score: f64,
+    /// Best score so far after every pass of the climb or round of the
+    /// annealing, for all runs one after the other
+    pub history: Vec<f64>,
+}
+
+/// Plugboard as a letter table, every letter mapped to its partner or itself
+type Plugs = [u8; 26];
+
+const NO_PLUGS: Plugs = {
+    let mut plugs = [0; 26];
+    let mut i = 0;
+    while i < 26 {
+        plugs[i] = i as u8;
+        i += 1;
+    }
+    plugs
+};
+
+#[derive(Clone)]
+pub struct PlugboardSearch<'a> {
+    ciphertext: Vec<u8>,
+    scramblers: Vec<[u8; 26]>,
+    scorer: &'a dyn Scorer,
+    cables: usize,
+    start: Steckerverbindungen,
+    fixed: Steckerverbindungen,
+    schedule: Schedule,
+    restarts: usize,
+    seed: u64,
+}
+
+impl<'a> PlugboardSearch<'a> {
+    /// Search for the plugs of `ciphertext` on a machine whose rotor cores
+    /// stand at `cores`, see [`Candidate`](super::checking::Candidate). By
+    /// default it climbs once on German bigrams with up to ten cables.
+    pub fn new(
+        ciphertext: &str,
+        walzenlage: Walzenlage,
+        rings: [u8; 3],
+        cores: [EnigmaAlphabet; 3],
+    ) -> Result<Self, AnalysisError> {
+        Ok(Self::from_letters(&letters(ciphertext)?, walzenlage, rings, cores))
+    }
+
+    pub(crate) fn from_letters(
+        ciphertext: &[EnigmaAlphabet],
+        walzenlage: Walzenlage,
+        rings: [u8; 3],
+        cores: [EnigmaAlphabet; 3],
+    ) -> Self {
+        let mut enigma = machine(walzenlage, rings, cores, Plugboard::default());
+        Self {
+            ciphertext: ciphertext.iter().map(|letter| letter.to_u8()).collect(),
+            scramblers: scramblers(&mut enigma, ciphertext.len())
+                .into_iter()
+                .map(|scrambler| scrambler.map(|letter| letter.to_u8()))
+                .collect(),
+            scorer: LanguageModel::german().ngrams(2),
+            cables: 10,
+            start: Steckerverbindungen::default(),
+            fixed: Steckerverbindungen::default(),
+            schedule: Schedule::HillClimb,
+            restarts: 0,
+            seed: 0,
+        }
+    }
+
+    pub fn with_scorer(mut self, scorer: &'a dyn Scorer) -> Self {
+        self.scorer = scorer;
+        self
+    }
+
+    /// Most pairs the plugboard may have, fixed pairs included
+    pub fn with_cables(mut self, cables: usize) -> Self {
+        self.cables = cables;
+        self
+    }
+
+    /// Pairs the first run starts from, free to move, as far as they fit the
+    /// fixed pairs and cables
+    pub fn with_start(mut self, start: Steckerverbindungen) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Pairs known for certain, e.g. from a bombe stop. [`run`](Self::run)
+    /// fails if they need more cables than allowed.
+    pub fn with_fixed(mut self, fixed: Steckerverbindungen) -> Self {
+        self.fixed = fixed;
+        self
+    }
+
+    /// Annealing temperatures must be positive, [`run`](Self::run) fails
+    /// otherwise
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Runs from random pairs after the first
+    pub fn with_restarts(mut self, restarts: usize, seed: u64) -> Self {
+        self.restarts = restarts;
+        self.seed = seed;
+        self
+    }
+
+    /// Score of the decrypt with `steckerverbindungen`
+    pub fn score(&self, steckerverbindungen: &Steckerverbindungen) -> f64 {
+        self.evaluate(&to_plugs(steckerverbindungen), &mut Vec::new())
+    }
+
+    pub fn run(&self) -> Result<PlugboardSolution, AnalysisError> {
+        if let Schedule::Annealing { start, end, .. } = self.schedule {
+            if !(start > 0.0 && end > 0.0) {
+                return Err(AnalysisError::InvalidSchedule);
+            }
+        }
+        if self.fixed.len() > self.cables {
+            return Err(AnalysisError::TooManyFixedPairs(self.fixed.len()));
+        }
+
+        let mut rng = Rng::new(self.seed);
+        let fixed = to_plugs(&self.fixed);
+        let mut history = Vec::new();
+        let mut best: Option<(Plugs, f64)> = None;
+        for run in 0..=self.restarts {
+            let mut plugs = fixed;
+            if run == 0 {
+                for (a, b) in self.start.pairs() {
+                    let (a, b) = (a.to_u8(), b.to_u8());
+                    if is_free(&plugs, a) && is_free(&plugs, b) && pairs(&plugs) < self.cables {
+                        connect(&mut plugs, a, b);
+                    }
+                }
+            } else {
+                let mut free: Vec<u8> = (0..26).filter(|&i| is_free(&plugs, i)).collect();
+                rng.shuffle(&mut free);
+                let cables = 5.min(self.cables.saturating_sub(self.fixed.len()));
+                for pair in free.chunks(2).take(cables) {
+                    connect(&mut plugs, pair[0], pair[1]);
+                }
+            }
+
+            let best_before = best.map_or(f64::NEG_INFINITY, |(_, score)| score);
+            let score = match self.schedule {
+                Schedule::HillClimb => self.climb(&mut plugs, &fixed, best_before, &mut history),
+                Schedule::Annealing { start, end, rounds } => {
+                    let schedule = (start, end, rounds);
+                    self.anneal(&mut plugs, &fixed, schedule, &mut rng, best_before, &mut history)
+                }
+            };
+            if best.is_none_or(|(_, best)| score > best) {
+                best = Some((plugs, score));
+            }
+        }
+
+        let (plugs, score) = best.expect("at least one run");
+        let pairs = (0..26u8)
+            .filter(|&i| plugs[usize::from(i)] > i)
+            .map(|i| (EnigmaAlphabet::new(i), EnigmaAlphabet::new(plugs[usize::from(i)])));
+        Ok(PlugboardSolution {
+            steckerverbindungen: Steckerverbindungen::from_pairs(pairs)
+                .expect("plugs pair distinct letters"),
+            score,
+            history,
+        })
+    }
+
+    fn evaluate(&self, plugs: &Plugs, buffer: &mut Vec<EnigmaAlphabet>) -> f64 {
+        buffer.clear();
+        buffer.extend(self.ciphertext.iter().zip(&self.scramblers).map(|(&letter, scrambler)| {
+            let letter = plugs[usize::from(scrambler[usize::from(plugs[usize::from(letter)])])];
+            EnigmaAlphabet::new(letter)
+        }));
+        self.scorer.score(buffer)
+    }
+
+    /// Moves pairing letters `i` and `j`, left out where they touch a fixed
+    /// pair or need more cables than allowed
+    fn moves(&self, plugs: &Plugs, fixed: &Plugs, i: u8, j: u8) -> [Option<Plugs>; 2] {
+        let mut moves = [None; 2];
+        if !is_free(fixed, i) || !is_free(fixed, j) {
+            return moves;
+        }
+        let (a, b) = (plugs[usize::from(i)], plugs[usize::from(j)]);
+        let mut plugs = *plugs;
+        if a == j {
+            disconnect(&mut plugs, i);
+            moves[0] = Some(plugs);
+        } else {
+            disconnect(&mut plugs, i);
+            disconnect(&mut plugs, j);
+            connect(&mut plugs, i, j);
+            moves[0] = Some(plugs);
+            if a != i && b != j {
+                connect(&mut plugs, a, b);
+                moves[1] = Some(plugs);
+            }
+        }
+        moves.map(|plugs| plugs.filter(|plugs| pairs(plugs) <= self.cables))
+    }
+
+    fn climb(&self, plugs: &mut Plugs, fixed: &Plugs, best: f64, history: &mut Vec<f64>) -> f64 {
+        let mut buffer = Vec::with_capacity(self.ciphertext.len());
+        let mut score = self.evaluate(plugs, &mut buffer);
+        loop {
+            let mut step = None;
+            for i in 0..26 {
+                for j in i + 1..26 {
+                    for plugs in self.moves(plugs, fixed, i, j).into_iter().flatten() {
+                        let candidate = self.evaluate(&plugs, &mut buffer);
+                        if candidate > score {
+                            score = candidate;
+                            step = Some(plugs);
+                        }
+                    }
+                }
+            }
+            history.push(score.max(best));
+            match step {
+                Some(step) => *plugs = step,
+                None => return score,
+            }
+        }
+    }
+
+    fn anneal(
+        &self,
+        plugs: &mut Plugs,
+        fixed: &Plugs,
+        (start, end, rounds): (f64, f64, usize),
+        rng: &mut Rng,
+        best_before: f64,
+        history: &mut Vec<f64>,
+    ) -> f64 {
+        let mut buffer = Vec::with_capacity(self.ciphertext.len());
+        let mut score = self.evaluate(plugs, &mut buffer);
+        let (mut best, mut best_score) = (*plugs, score);
+        let cooling = (end / start).powf(1.0 / rounds.saturating_sub(1).max(1) as f64);
+        let mut temperature = start;
+        for _ in 0..rounds {
+            for i in 0..26 {
+                for j in i + 1..26 {
+                    let moves = self.moves(plugs, fixed, i, j);
+                    let Some(candidate) = moves[rng.below(2)].or(moves[0]) else {
+                        continue;
+                    };
+                    let candidate_score = self.evaluate(&candidate, &mut buffer);
+                    let delta = candidate_score - score;
+                    if delta >= 0.0 || rng.next_f64() < (delta / temperature).exp() {
+                        *plugs = candidate;
+                        score = candidate_score;
+                        if score > best_score {
+                            (best, best_score) = (candidate, score);
+                        }
+                    }
+                }
+            }
+            history.push(best_score.max(best_before));
+            temperature *= cooling;
+        }
+        *plugs = best;
+        self.climb(plugs, fixed, best_before, history)
+    }
+}
+
+fn to_plugs(steckerverbindungen: &Steckerverbindungen) -> Plugs {
+    let mut plugs = NO_PLUGS;
+    for (a, b) in steckerverbindungen.pairs() {
+        connect(&mut plugs, a.to_u8(), b.to_u8());
+    }
+    plugs
+}
+
+fn is_free(plugs: &Plugs, letter: u8) -> bool {
+    plugs[usize::from(letter)] == letter
+}
+
+fn connect(plugs: &mut Plugs, a: u8, b: u8) {
+    plugs[usize::from(a)] = b;
+    plugs[usize::from(b)] = a;
+}
+
+fn disconnect(plugs: &mut Plugs, letter: u8) {
+    let partner = plugs[usize::from(letter)];
+    plugs[usize::from(letter)] = letter;
+    plugs[usize::from(partner)] = partner;
+}
+
+fn pairs(plugs: &Plugs) -> usize {
+    (0..26).filter(|&i| usize::from(plugs[i]) > i).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::language::IndexOfCoincidence;
+    use crate::key::KeyError;
+
+    const CIPHERTEXT: &str = "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY SIOZV EQMIK UBPMM YLKLT TDEIS \
+                              MDICA GYKUA CTCDO MOHWX MUUIA UBSTS LRNBZ SZWNR FXWFY SSXJZ VIJHI DISHP RKLKA YUPAD \
+                              TXQSP INQMA TLPIF SVKDA SCTAC DPBOP VHJK";
+
+    fn search() -> PlugboardSearch<'static> {
+        PlugboardSearch::new(
+            CIPHERTEXT,
+            "B II IV V".parse().unwrap(),
+            [1, 20, 11],
+            ['A', 'R', 'P'].map(EnigmaAlphabet::from),
+        )
+        .unwrap()
+    }
+
+    fn steckers(pairs: &str) -> Steckerverbindungen {
+        pairs.parse().unwrap()
+    }
+
+    #[test]
+    fn test_hill_climb_operation_barbarossa() {
+        let solution = search().run().unwrap();
+        assert_eq!(solution.steckerverbindungen, steckers("AV BS CG DL FU HZ IN KM OW RX"));
+        assert_eq!(solution.score, search().score(&solution.steckerverbindungen));
+        assert!(solution.history.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(solution.history.last(), Some(&solution.score));
+    }
+
+    #[test]
+    fn test_annealing_keeps_fixed_pairs() {
+        // a wrong pair the search may not undo
+        let solution = search()
+            .with_fixed(steckers("AB"))
+            .with_schedule(Schedule::Annealing { start: 10.0, end: 0.5, rounds: 10 })
+            .with_restarts(1, 7)
+            .run()
+            .unwrap();
+        assert!(solution.steckerverbindungen.pairs().any(|pair| pair == ('A'.into(), 'B'.into())));
+        assert!(solution.steckerverbindungen.len() <= 10);
+        assert!(solution.history.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let solution = search()
+            .with_fixed(steckers("AV HZ"))
+            .with_schedule(Schedule::Annealing { start: 30.0, end: 3.0, rounds: 50 })
+            .with_restarts(2, 1)
+            .run()
+            .unwrap();
+        assert_eq!(solution.steckerverbindungen, steckers("AV BS CG DL FU HZ IN KM OW RX"));
+    }
+
+    #[test]
+    fn test_invalid_search() {
+        for (start, end) in [(10.0, 0.0), (-1.0, 0.5), (f64::NAN, 0.5)] {
+            let search = search().with_schedule(Schedule::Annealing { start, end, rounds: 10 });
+            assert_eq!(search.run(), Err(AnalysisError::InvalidSchedule));
+        }
+
+        let search = search().with_fixed(steckers("AV BS CG")).with_cables(2);
+        assert_eq!(search.run(), Err(AnalysisError::TooManyFixedPairs(3)));
+        // fixed pairs cannot overlap, the plugboard rejects a letter plugged twice
+        assert_eq!(
+            "AV VB".parse::<Steckerverbindungen>(),
+            Err(KeyError::RepeatedLetter('V'.into()))
+        );
+    }
+
+    #[test]
+    fn test_cables_and_scorer() {
+        let solution = search().with_cables(0).with_start(steckers("AV BS")).run().unwrap();
+        assert!(solution.steckerverbindungen.is_empty());
+
+        let solution = search().with_scorer(&IndexOfCoincidence).with_cables(3).run().unwrap();
+        assert!(solution.steckerverbindungen.len() <= 3);
+        assert!(solution.score > search().with_scorer(&IndexOfCoincidence).score(&steckers("")));
+    }
+}
@@ -0,0 +1,277 @@
+//! Ring setting recovery
+//!
+//! Searches over start positions usually assume the rings at `A`, which finds
+//! the rotor cores but not when the middle rotor really turns over. A key
+//! found that way decrypts correctly only while the middle rotor stands where
+//! it would with the true rings. Every time the right rotor passes the assumed
+//! turnover but not yet the true one, or the other way round, the decrypt goes
+//! garbled until the two catch up again, once every 26 letters. The same
+//! happens to the left rotor with the ring of the middle one, if the middle
+//! rotor reaches its turnover within the message.
+//!
+//! Each ring setting of the middle and right rotor predicts where the decrypt
+//! with the assumed rings is garbled. The setting whose prediction fits best
+//! what the text shows wins: the letters of a crib or menu that come out
+//! wrong, or without a crib the stretches that read unlike the language. The
+//! key is returned with those rings and the Grundstellung moved to match, so
+//! that it turns the same rotor cores. The ring of the left rotor never shows
+//! and is kept.
+
+use core::ops::Range;
+
+use super::language::NgramTable;
+use super::letters;
+use super::machine;
+use super::menu::Menu;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::parts::plugboard::Plugboard;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::key::Ringstellung;
+
+/// What tells right letters of a decrypt from garbled ones
+#[derive(Debug, Clone, Copy)]
+pub enum Evidence<'a> {
+    /// Known plaintext starting `offset` letters into the message
+    Crib { plaintext: &'a str, offset: usize },
+    /// Known plaintext letters of a menu, offsets counted from the start of
+    /// the message
+    Menu(&'a Menu),
+    /// N-grams of the language of the plaintext
+    Language(&'a NgramTable),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingRecovery {
+    /// The key with the recovered rings and the Grundstellung that keeps the
+    /// rotor cores where they were
+    pub key: DailyKey,
+    /// Offsets the assumed rings step differently, where their decrypt was
+    /// garbled
+    pub disruptions: Vec<Range<usize>>,
+    pub plaintext: String,
+    /// Letters whose evidence agrees with the prediction of the recovered
+    /// rings, out of those with evidence
+    pub agreement: (usize, usize),
+}
+
+/// Rotor cores at each letter of a message `len` letters long
+pub(super) fn cores(
+    key: &DailyKey,
+    cores: [EnigmaAlphabet; 3],
+    len: usize,
+) -> Vec<[EnigmaAlphabet; 3]> {
+    let rings = key.ringstellung.0;
+    let mut enigma = machine(key.walzenlage, rings, cores, Plugboard::default());
+    (0..len)
+        .map(|_| {
+            enigma.step();
+            let mut positions = enigma.positions();
+            for (position, ring) in positions.iter_mut().zip(rings) {
+                *position -= ring;
+            }
+            positions
+        })
+        .collect()
+}
+
+/// Whether each letter of the decrypt looks right, `None` without evidence
+fn observations(
+    decrypt: &[EnigmaAlphabet],
+    evidence: Evidence,
+) -> Result<Vec<Option<bool>>, AnalysisError> {
+    let mut observed = vec![None; decrypt.len()];
+    match evidence {
+        Evidence::Crib { plaintext, offset } => {
+            let crib = letters(plaintext)?;
+            if crib.is_empty() {
+                return Err(AnalysisError::EmptyCrib);
+            }
+            if offset.checked_add(crib.len()).is_none_or(|end| end > decrypt.len()) {
+                return Err(AnalysisError::TooShort);
+            }
+            for (i, letter) in crib.into_iter().enumerate() {
+                observed[offset + i] = Some(decrypt[offset + i] == letter);
+            }
+        }
+        Evidence::Menu(menu) => {
+            if menu.edges.is_empty() {
+                return Err(AnalysisError::EmptyMenu);
+            }
+            for edge in &menu.edges {
+                let decrypted = decrypt.get(edge.offset).ok_or(AnalysisError::TooShort)?;
+                observed[edge.offset] = Some(*decrypted == edge.plain);
+            }
+        }
+        Evidence::Language(table) => {
+            let n = table.n();
+            if decrypt.len() < n {
+                return Err(AnalysisError::TooShort);
+            }
+            // halfway between what text of the language and random letters
+            // score per n-gram
+            let (mut language, mut random) = (0.0, 0.0);
+            for i in 0..26usize.pow(n as u32) {
+                let ngram: Vec<_> = (0..n)
+                    .rev()
+                    .map(|digit| EnigmaAlphabet::new((i / 26usize.pow(digit as u32) % 26) as u8))
+                    .collect();
+                let log_probability = table.log_probability(&ngram);
+                language += log_probability.exp() * log_probability;
+                random += log_probability;
+            }
+            let threshold = (language + random / 26f64.powi(n as i32)) / 2.0;
+
+            let scores: Vec<_> =
+                decrypt.windows(n).map(|ngram| table.log_probability(ngram) - threshold).collect();
+            for (i, observation) in observed.iter_mut().enumerate() {
+                let around = &scores[i.saturating_sub(n - 1)..(i + 1).min(scores.len())];
+                *observation = Some(around.iter().sum::<f64>() > 0.0);
+            }
+        }
+    }
+    Ok(observed)
+}
+
+/// Recover the middle and right rings of `key`, which must have a
+/// Grundstellung and otherwise be right
+pub fn recover_rings(
+    ciphertext: &str,
+    key: &DailyKey,
+    evidence: Evidence,
+) -> Result<RingRecovery, AnalysisError> {
+    recover_rings_from_letters(&letters(ciphertext)?, key, evidence)
+}
+
+pub(crate) fn recover_rings_from_letters(
+    ciphertext: &[EnigmaAlphabet],
+    key: &DailyKey,
+    evidence: Evidence,
+) -> Result<RingRecovery, AnalysisError> {
+    let grundstellung = key.grundstellung.ok_or(AnalysisError::MissingGrundstellung)?;
+    let rings = key.ringstellung.0;
+    let mut start = grundstellung.0;
+    for (position, ring) in start.iter_mut().zip(rings) {
+        *position -= ring;
+    }
+
+    let plugboard = Plugboard::from(key.steckerverbindungen);
+    let mut enigma = machine(key.walzenlage, rings, start, plugboard);
+    let decrypt: Vec<_> = ciphertext.iter().map(|&letter| enigma.encode(letter)).collect();
+    let observed = observations(&decrypt, evidence)?;
+    let assumed = cores(key, start, ciphertext.len());
+
+    // starting from the assumed rings, so that rings the message cannot tell
+    // apart stay as they were
+    let mut best: Option<(DailyKey, Vec<bool>, usize)> = None;
+    for middle in (0..26).map(|i| (rings[1] + i) % 26) {
+        for right in (0..26).map(|i| (rings[2] + i) % 26) {
+            let candidate =
+                DailyKey { ringstellung: Ringstellung([rings[0], middle, right]), ..*key };
+            let agrees: Vec<bool> = cores(&candidate, start, ciphertext.len())
+                .iter()
+                .zip(&assumed)
+                .map(|(cores, assumed)| cores == assumed)
+                .collect();
+            let agreement = agrees
+                .iter()
+                .zip(&observed)
+                .filter(|(&agrees, observed)| **observed == Some(agrees))
+                .count();
+            if best.as_ref().is_none_or(|(_, _, best)| agreement > *best) {
+                best = Some((candidate, agrees, agreement));
+            }
+        }
+    }
+
+    let (mut key, agrees, agreement) = best.expect("26 by 26 ring settings were tried");
+    let mut positions = start;
+    for (position, ring) in positions.iter_mut().zip(key.ringstellung.0) {
+        *position += ring;
+    }
+    key.grundstellung = Some(Grundstellung(positions));
+
+    let mut disruptions: Vec<Range<usize>> = Vec::new();
+    for (i, _) in agrees.iter().enumerate().filter(|(_, &agrees)| !agrees) {
+        match disruptions.last_mut() {
+            Some(range) if range.end == i => range.end += 1,
+            _ => disruptions.push(i..i + 1),
+        }
+    }
+
+    let ringstellung = key.ringstellung.0;
+    let mut enigma = machine(key.walzenlage, ringstellung, start, plugboard);
+    let plaintext = ciphertext.iter().map(|&letter| enigma.encode(letter).to_char()).collect();
+    Ok(RingRecovery {
+        key,
+        disruptions,
+        plaintext,
+        agreement: (agreement, observed.iter().flatten().count()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::crib;
+    use crate::analysis::language::LanguageModel;
+
+    const CIPHERTEXT: &str = "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY SIOZV EQMIK UBPMM YLKLT TDEIS \
+                              MDICA GYKUA CTCDO MOHWX MUUIA UBSTS LRNBZ SZWNR FXWFY SSXJZ VIJHI DISHP RKLKA YUPAD \
+                              TXQSP INQMA TLPIF SVKDA SCTAC DPBOP VHJK";
+    const PLAINTEXT: &str = "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAXNORDWESTLXSEBEZXSEBEZXUAFFLIEGERSTRASZERIQTUNGX\
+                             DUBROWKIXDUBROWKIXOPOTSCHKAXOPOTSCHKAXUMXEINSAQTDREINULLXUHRANGETRETENXANGRIFFXINFXRGTX";
+
+    /// The cores of the true key BLA with rings 02 21 12, assuming rings at 01
+    fn assumed() -> DailyKey {
+        "B II IV V 01 01 01 AV BS CG DL FU HZ IN KM OW RX ARP".parse().unwrap()
+    }
+
+    fn check(recovery: &RingRecovery) {
+        assert_eq!(recovery.plaintext, PLAINTEXT);
+        assert_eq!(recovery.key.ringstellung.0[2], 11);
+        assert_eq!(recovery.key.grundstellung.unwrap().0[2], 'A'.into());
+        // assumed, the middle rotor moves at the 11th letter instead of the 26th
+        assert_eq!(recovery.disruptions[..2], [10..25, 36..51]);
+    }
+
+    #[test]
+    fn test_recover_rings_from_crib() {
+        let crib = Evidence::Crib { plaintext: &PLAINTEXT[..40], offset: 0 };
+        let recovery = recover_rings(CIPHERTEXT, &assumed(), crib).unwrap();
+        check(&recovery);
+        assert_eq!(recovery.agreement, (40, 40));
+        // the middle rotor never reaches its turnover, its ring stays
+        assert_eq!(recovery.key.ringstellung.0[..2], [0, 0]);
+    }
+
+    #[test]
+    fn test_recover_rings_from_menu() {
+        let placement = crib::place(CIPHERTEXT, &PLAINTEXT[..40]).unwrap().remove(0);
+        let menu = Menu::from_placement(&placement);
+        let recovery = recover_rings(CIPHERTEXT, &assumed(), Evidence::Menu(&menu)).unwrap();
+        check(&recovery);
+        assert_eq!(recovery.agreement, (40, 40));
+    }
+
+    #[test]
+    fn test_recover_rings_ciphertext_only() {
+        let language = Evidence::Language(LanguageModel::german().ngrams(2));
+        check(&recover_rings(CIPHERTEXT, &assumed(), language).unwrap());
+    }
+
+    #[test]
+    fn test_recover_rings_errors() {
+        let key = DailyKey { grundstellung: None, ..assumed() };
+        let language = Evidence::Language(LanguageModel::german().ngrams(2));
+        assert_eq!(
+            recover_rings(CIPHERTEXT, &key, language),
+            Err(AnalysisError::MissingGrundstellung)
+        );
+        let crib = Evidence::Crib { plaintext: "GTX", offset: 172 };
+        assert_eq!(recover_rings(CIPHERTEXT, &assumed(), crib), Err(AnalysisError::TooShort));
+        let crib = Evidence::Crib { plaintext: "GTX", offset: usize::MAX };
+        assert_eq!(recover_rings(CIPHERTEXT, &assumed(), crib), Err(AnalysisError::TooShort));
+    }
+}
@@ -0,0 +1,40 @@
+//! The day the tests of the indicator methods share
+
+use super::zygalski::Indicator;
+use crate::core::engine::Enigma;
+use crate::historical_machines::model::Model;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::random::OperatorHabit;
+use crate::random::Rng;
+
+/// Key of the day, without a Grundstellung
+pub(super) fn key() -> DailyKey {
+    "B II I III 24 13 05 AM FI NV PS TU WZ".parse().unwrap()
+}
+
+/// The day's key at `grundstellung`
+pub(super) fn key_at(grundstellung: &str) -> DailyKey {
+    DailyKey { grundstellung: Some(grundstellung.parse().unwrap()), ..key() }
+}
+
+/// Messages of the day with a random start position and careful message
+/// keys, leaving out those where the middle rotor moves during the indicator
+pub(super) fn indicators(seed: u64) -> impl Iterator<Item = Indicator> {
+    let mut rng = Rng::new(seed);
+    core::iter::from_fn(move || loop {
+        let start = [rng.letter(), rng.letter(), rng.letter()];
+        let message_key = rng.message_key(OperatorHabit::Careful);
+        let key = DailyKey { grundstellung: Some(Grundstellung(start)), ..key() };
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &key).unwrap();
+        let doubled: Vec<_> = message_key
+            .0
+            .iter()
+            .chain(&message_key.0)
+            .map(|&letter| enigma.encode(letter))
+            .collect();
+        if enigma.positions()[1] == start[1] {
+            return Some(Indicator { start, doubled: doubled.try_into().unwrap() });
+        }
+    })
+}
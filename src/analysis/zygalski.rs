@@ -0,0 +1,299 @@
+//! Zygalski sheets
+//!
+//! From September 1938 the operator picked a start position for every message
+//! and sent it in the clear, followed by the message key typed twice at that
+//! start. The ring setting stayed secret, so the clear start told the rotor
+//! positions only up to an unknown offset that was the same for the whole day.
+//!
+//! Sometimes the same letter turns up twice in the doubled key, at positions
+//! 1 and 4, 2 and 5 or 3 and 6: a female. That can only happen where the
+//! product of the two permutations has a fixed point, which the plugboard does
+//! not change, and only about two positions in five allow it. A sheet belongs
+//! to a wheel order and a position of the left rotor core, with a hole for
+//! every position of the middle and right cores that allows a female at 1 and
+//! 4. A female at 2 and 5 is one at 1 and 4 a letter later, and the same for 3
+//! and 6.
+//!
+//! Each female, moved back by the ring setting, has to fall on a hole. Laying
+//! the sheets of the day's females on top of each other, shifted by their
+//! clear starts, light shines through only where the ring setting is right.
+//! The sheets assume that the middle rotor does not move during the indicator,
+//! and a female where it does may hide the true setting.
+
+use core::fmt;
+use core::str::FromStr;
+
+use super::letters;
+use super::machine;
+use super::AnalysisError;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::parts::plugboard::Plugboard;
+use crate::key::DailyKey;
+use crate::key::Ringstellung;
+use crate::key::Steckerverbindungen;
+use crate::key::Walzenlage;
+
+/// Clear start and doubled message key at the head of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Indicator {
+    pub start: [EnigmaAlphabet; 3],
+    pub doubled: [EnigmaAlphabet; 6],
+}
+
+impl Indicator {
+    /// Letter pairs, 0 for positions 1 and 4 up to 2 for 3 and 6, that repeat
+    pub fn females(&self) -> impl Iterator<Item = Female> + '_ {
+        (0..3)
+            .filter(|&pair| self.doubled[pair] == self.doubled[pair + 3])
+            .map(|pair| Female { start: self.start, pair })
+    }
+}
+
+impl fmt::Display for Indicator {
+    /// E.g. `RTJ WAHWIK`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start: String = self.start.iter().map(|letter| letter.to_char()).collect();
+        let doubled: String = self.doubled.iter().map(|letter| letter.to_char()).collect();
+        write!(f, "{start} {doubled}")
+    }
+}
+
+impl FromStr for Indicator {
+    type Err = AnalysisError;
+
+    /// The clear start and the six letters, spaces anywhere
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let letters = letters(s)?;
+        let [s1, s2, s3, d @ ..]: [EnigmaAlphabet; 9] =
+            letters.try_into().map_err(|_| AnalysisError::InvalidIndicator(0))?;
+        Ok(Self { start: [s1, s2, s3], doubled: d })
+    }
+}
+
+/// A repeated letter of a doubled message key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Female {
+    /// Clear start of the message
+    pub start: [EnigmaAlphabet; 3],
+    /// 0 for positions 1 and 4, 1 for 2 and 5, 2 for 3 and 6
+    pub pair: usize,
+}
+
+/// Females among the indicators of a day
+pub fn females(indicators: &[Indicator]) -> Vec<Female> {
+    indicators.iter().flat_map(Indicator::females).collect()
+}
+
+/// Perforated sheet of a wheel order and left rotor core
+#[derive(Clone, PartialEq, Eq)]
+pub struct Sheet {
+    pub walzenlage: Walzenlage,
+    pub left: EnigmaAlphabet,
+    /// Indexed by middle then right rotor core
+    holes: [[bool; 26]; 26],
+}
+
+impl Sheet {
+    pub fn new(walzenlage: Walzenlage, left: EnigmaAlphabet) -> Self {
+        let permutation = |middle: u8, right: u8| {
+            let cores = [left, EnigmaAlphabet::new(middle), EnigmaAlphabet::new(right % 26)];
+            machine(walzenlage, [0; 3], cores, Plugboard::default()).permutation()
+        };
+        let mut holes = [[false; 26]; 26];
+        for middle in 0..26 {
+            for right in 0..26 {
+                // the rotor steps before the first letter
+                let (first, fourth) =
+                    (permutation(middle, right + 1), permutation(middle, right + 4));
+                holes[usize::from(middle)][usize::from(right)] =
+                    (0..26).any(|i| usize::from(fourth[usize::from(first[i])]) == i);
+            }
+        }
+        Self { walzenlage, left, holes }
+    }
+
+    /// Whether the sheet is perforated at the given middle and right cores
+    pub fn hole(&self, middle: EnigmaAlphabet, right: EnigmaAlphabet) -> bool {
+        self.holes[usize::from(middle)][usize::from(right)]
+    }
+
+    /// Number of holes, about two in five of the 676 positions
+    pub fn holes(&self) -> usize {
+        self.holes.iter().flatten().filter(|&&hole| hole).count()
+    }
+
+    /// Plain PBM image, `cell` pixels to a position, black where the paper is
+    /// whole
+    pub fn to_pbm(&self, cell: usize) -> String {
+        let size = 26 * cell;
+        let mut pbm = format!("P1\n{size} {size}\n");
+        for row in &self.holes {
+            let line: Vec<&str> = row
+                .iter()
+                .flat_map(|&hole| core::iter::repeat_n(if hole { "0" } else { "1" }, cell))
+                .collect();
+            let line = line.join(" ");
+            for _ in 0..cell {
+                pbm.push_str(&line);
+                pbm.push('\n');
+            }
+        }
+        pbm
+    }
+}
+
+impl fmt::Debug for Sheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sheet")
+            .field("walzenlage", &self.walzenlage)
+            .field("left", &self.left)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Sheet {
+    /// The right rotor across and the middle rotor down, `o` for a hole
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} / {}", self.walzenlage, self.left.to_char())?;
+        let header: String = (0..26).map(|i| EnigmaAlphabet::new(i).to_char()).collect();
+        write!(f, "  {header}")?;
+        for (middle, row) in self.holes.iter().enumerate() {
+            let row: String = row.iter().map(|&hole| if hole { 'o' } else { '.' }).collect();
+            write!(f, "\n{} {row}", EnigmaAlphabet::new(middle as u8).to_char())?;
+        }
+        Ok(())
+    }
+}
+
+/// The 26 sheets of a wheel order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SheetSet {
+    pub walzenlage: Walzenlage,
+    sheets: Vec<Sheet>,
+}
+
+/// Ring setting where the holes of every female line up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub walzenlage: Walzenlage,
+    pub rings: [u8; 3],
+}
+
+impl Alignment {
+    /// The rotor part of the daily key, no plugs and no Grundstellung since
+    /// every message has its own
+    pub fn daily_key(&self) -> DailyKey {
+        DailyKey {
+            walzenlage: self.walzenlage,
+            ringstellung: Ringstellung(self.rings),
+            steckerverbindungen: Steckerverbindungen::default(),
+            grundstellung: None,
+        }
+    }
+}
+
+impl SheetSet {
+    pub fn new(walzenlage: Walzenlage) -> Self {
+        let sheets =
+            (0..26).map(|left| Sheet::new(walzenlage, EnigmaAlphabet::new(left))).collect();
+        Self { walzenlage, sheets }
+    }
+
+    pub fn sheet(&self, left: EnigmaAlphabet) -> &Sheet {
+        &self.sheets[usize::from(left)]
+    }
+
+    /// Stack the sheets of `females` for every ring setting and keep those
+    /// where light comes through all of them
+    pub fn stack(&self, females: &[Female]) -> Vec<Alignment> {
+        let mut alignments = Vec::new();
+        if females.is_empty() {
+            return alignments;
+        }
+        for rings in 0..26 * 26 * 26 {
+            let rings = [rings / 676, rings / 26 % 26, rings % 26].map(|ring| ring as u8);
+            let aligned = females.iter().all(|female| {
+                let [left, middle, right] = female.start;
+                let sheet = self.sheet(left - rings[0]);
+                sheet.hole(middle - rings[1], right - rings[2] + female.pair as u8)
+            });
+            if aligned {
+                alignments.push(Alignment { walzenlage: self.walzenlage, rings });
+            }
+        }
+        alignments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::test_day;
+    use crate::analysis::test_day::key;
+    use crate::core::engine::Enigma;
+    use crate::historical_machines::model::Model;
+    use crate::key::Grundstellung;
+
+    fn indicators(count: usize) -> Vec<Indicator> {
+        test_day::indicators(1939).take(count).collect()
+    }
+
+    #[test]
+    fn test_indicator_and_females() {
+        let indicator: Indicator = "RTJ WAHWIK".parse().unwrap();
+        assert_eq!(indicator.to_string(), "RTJ WAHWIK");
+        assert_eq!(indicator.females().map(|female| female.pair).collect::<Vec<_>>(), [0]);
+        assert_eq!("RTJ WAHWI".parse::<Indicator>(), Err(AnalysisError::InvalidIndicator(0)));
+        assert_eq!(females(&["ABC DEFGHI".parse().unwrap()]), []);
+    }
+
+    #[test]
+    fn test_sheet() {
+        let sheet = Sheet::new(key().walzenlage, 'A'.into());
+        assert!((200..350).contains(&sheet.holes()), "{} holes", sheet.holes());
+        let ascii = sheet.to_string();
+        assert_eq!(ascii.lines().count(), 28);
+        assert!(ascii.starts_with("B II I III / A\n  ABCDEFGHIJKLMNOPQRSTUVWXYZ\nA "));
+        let pbm = sheet.to_pbm(2);
+        assert!(pbm.starts_with("P1\n52 52\n"));
+        assert_eq!(pbm.lines().count(), 2 + 52);
+
+        // every female falls on a hole of its sheet at the true ring setting
+        let rings = key().ringstellung.0;
+        let sheets = SheetSet::new(key().walzenlage);
+        for female in females(&indicators(200)) {
+            let [left, middle, right] = female.start;
+            let sheet = sheets.sheet(left - rings[0]);
+            assert!(sheet.hole(middle - rings[1], right - rings[2] + female.pair as u8));
+        }
+    }
+
+    #[test]
+    fn test_stack_sheets() {
+        let females = females(&indicators(150));
+        assert!(females.len() >= 10, "{} females", females.len());
+
+        let mut alignments = Vec::new();
+        for walzenlage in ["B I II III", "B II I III", "B III II I"] {
+            alignments.extend(SheetSet::new(walzenlage.parse().unwrap()).stack(&females));
+        }
+        assert!(alignments.len() <= 3, "{alignments:?}");
+        let found = alignments
+            .iter()
+            .find(|alignment| alignment.walzenlage == key().walzenlage)
+            .expect("light comes through at the true ring setting");
+        assert_eq!(found.rings, key().ringstellung.0);
+
+        // with the rings and plugs, the doubled key reads twice the same
+        let indicator = indicators(1)[0];
+        let daily_key = DailyKey {
+            steckerverbindungen: key().steckerverbindungen,
+            grundstellung: Some(Grundstellung(indicator.start)),
+            ..found.daily_key()
+        };
+        let mut enigma = Enigma::from_key(Model::EnigmaI, &daily_key).unwrap();
+        let message_key: Vec<_> =
+            indicator.doubled.iter().map(|&letter| enigma.encode(letter)).collect();
+        assert_eq!(message_key[..3], message_key[3..]);
+    }
+}
@@ -0,0 +1,259 @@
+//! # Plaintext conventions
+//!
+//! The machine only has the letters `A`-`Z`, so operators wrote German
+//! according to fixed rules before typing it:
+//!
+//! - `X` separates words and ends sentences
+//! - `CH` is written `Q`, umlauts as `AE`, `OE`, `UE` and `ß` as `SZ`
+//! - proper names are written twice, `SEBEZXSEBEZ`
+//! - the Heer spells numbers digit by digit, `EINSAQTDREINULL` for 1830, using
+//!   `ZWO` so it cannot be misheard as `DREI`
+//! - the Kriegsmarine encloses digits in `Y` and types them on the top row of
+//!   the keyboard, `YQIEPY` for 1830
+//!
+//! [`Conventions::encode`] prepares plaintext for the machine,
+//! [`Conventions::decode`] makes a raw decrypt readable again.
+
+use core::fmt;
+
+/// Digits as spelled out by the Heer, [`Numbers::SpelledOut`]
+const DIGIT_WORDS: [&str; 10] =
+    ["NULL", "EINS", "ZWO", "DREI", "VIER", "FUENF", "SEQS", "SIEBEN", "AQT", "NEUN"];
+
+/// Other spellings of digits seen in decrypts
+const DIGIT_VARIANTS: [(&str, u8); 4] = [("ZWEI", 2), ("FUNF", 5), ("SECHS", 6), ("ACHT", 8)];
+
+/// Top row of the keyboard, digits 1 to 9 and 0 for [`Numbers::TopRow`]
+const TOP_ROW: [u8; 10] = *b"PQWERTZUIO";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    Heer,
+    Kriegsmarine,
+}
+
+/// How numbers are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Numbers {
+    /// Digit by digit as German words, `EINSAQT`
+    SpelledOut,
+    /// Between two `Y`, digits on the top row of the keyboard, `YQIY`
+    TopRow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConventionError {
+    /// The character has no spelling with the letters of the machine
+    UnsupportedChar(char),
+}
+
+impl fmt::Display for ConventionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConventionError::UnsupportedChar(ch) => {
+                write!(f, "{ch:?} cannot be written for the machine")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConventionError {}
+
+/// Rules for writing plaintext, see the [module docs](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conventions {
+    pub numbers: Numbers,
+    /// Write proper names twice. Names are recognised in the input as words
+    /// of two or more letters written entirely in capitals.
+    pub double_names: bool,
+}
+
+impl Conventions {
+    pub const fn new(service: Service) -> Self {
+        match service {
+            Service::Heer => Self { numbers: Numbers::SpelledOut, double_names: true },
+            Service::Kriegsmarine => Self { numbers: Numbers::TopRow, double_names: false },
+        }
+    }
+
+    /// Write `text` with the letters `A`-`Z` only. Spaces and punctuation
+    /// become `X`, a text ending a sentence ends with `X`.
+    pub fn encode(&self, text: &str) -> Result<String, ConventionError> {
+        let mut words = Vec::new();
+        let mut ends_sentence = false;
+        let mut chars = text.chars().peekable();
+        while let Some(&ch) = chars.peek() {
+            if ch.is_ascii_digit() {
+                let digits: String =
+                    core::iter::from_fn(|| chars.next_if(char::is_ascii_digit)).collect();
+                words.push(self.encode_number(&digits));
+            } else if ch.is_alphabetic() {
+                let word: String =
+                    core::iter::from_fn(|| chars.next_if(|ch| ch.is_alphabetic())).collect();
+                let letters = letters(&word)?;
+                let is_name = word.chars().count() > 1 && word.chars().all(char::is_uppercase);
+                if self.double_names && is_name {
+                    words.push(letters.clone());
+                }
+                words.push(letters);
+            } else if ch.is_whitespace() || ch.is_ascii_punctuation() {
+                chars.next();
+            } else {
+                return Err(ConventionError::UnsupportedChar(ch));
+            }
+            ends_sentence = matches!(ch, '.' | '!' | '?') || (ends_sentence && ch.is_whitespace());
+        }
+
+        let mut text = words.join("X");
+        if ends_sentence && !text.is_empty() {
+            text.push('X');
+        }
+        Ok(text)
+    }
+
+    /// Render a decrypt as words separated by spaces, with numbers restored,
+    /// `Q` written as `CH` and doubled names written once
+    pub fn decode(&self, letters: &str) -> String {
+        let mut words: Vec<String> = Vec::new();
+        for word in letters.split('X').filter(|word| !word.is_empty()) {
+            let word = match self.numbers {
+                Numbers::SpelledOut => {
+                    spelled_out_number(word).unwrap_or_else(|| word.replace('Q', "CH"))
+                }
+                Numbers::TopRow => top_row_numbers(word).replace('Q', "CH"),
+            };
+            if self.double_names
+                && words.last() == Some(&word)
+                && !word.bytes().all(|b| b.is_ascii_digit())
+            {
+                continue;
+            }
+            words.push(word);
+        }
+        words.join(" ")
+    }
+
+    fn encode_number(&self, digits: &str) -> String {
+        let digits = digits.bytes().map(|digit| usize::from(digit - b'0'));
+        match self.numbers {
+            Numbers::SpelledOut => digits.map(|digit| DIGIT_WORDS[digit]).collect(),
+            Numbers::TopRow => {
+                let digits: String = digits.map(|digit| char::from(TOP_ROW[digit])).collect();
+                format!("Y{digits}Y")
+            }
+        }
+    }
+}
+
+impl From<Service> for Conventions {
+    fn from(value: Service) -> Self {
+        Self::new(value)
+    }
+}
+
+/// Letters of a word in capitals, with umlauts, `ß` and `CH` replaced
+fn letters(word: &str) -> Result<String, ConventionError> {
+    let mut letters = String::new();
+    for ch in word.chars() {
+        // 'ß' would upper case to "SS"
+        let ch = if ch == 'ß' { ch } else { ch.to_uppercase().next().unwrap_or(ch) };
+        match ch {
+            ch @ 'A'..='Z' => letters.push(ch),
+            'Ä' => letters.push_str("AE"),
+            'Ö' => letters.push_str("OE"),
+            'Ü' => letters.push_str("UE"),
+            'ß' | 'ẞ' => letters.push_str("SZ"),
+            _ => return Err(ConventionError::UnsupportedChar(ch)),
+        }
+    }
+    Ok(letters.replace("CH", "Q"))
+}
+
+/// Digits of a word made up entirely of spelled out digits
+fn spelled_out_number(mut word: &str) -> Option<String> {
+    let spellings =
+        (0..).zip(DIGIT_WORDS).chain(DIGIT_VARIANTS.map(|(spelling, digit)| (digit, spelling)));
+    let mut digits = String::new();
+    while !word.is_empty() {
+        let (digit, rest) = spellings
+            .clone()
+            .find_map(|(digit, spelling)| word.strip_prefix(spelling).map(|rest| (digit, rest)))?;
+        digits.push(char::from(b'0' + digit));
+        word = rest;
+    }
+    (!digits.is_empty()).then_some(digits)
+}
+
+/// Replace every `Y...Y` run of top row letters in `word` by its digits
+fn top_row_numbers(word: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = word;
+    while let Some(start) = rest.find('Y') {
+        let after = &rest[start + 1..];
+        let digits = after
+            .find('Y')
+            .map(|end| &after[..end])
+            .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| TOP_ROW.contains(&b)));
+        match digits {
+            Some(digits) => {
+                decoded.push_str(&rest[..start]);
+                let position =
+                    |letter| TOP_ROW.iter().position(|&key| key == letter).expect("top row letter");
+                decoded
+                    .extend(digits.bytes().map(|letter| char::from(b'0' + position(letter) as u8)));
+                rest = &after[digits.len() + 1..];
+            }
+            None => {
+                decoded.push_str(&rest[..=start]);
+                rest = after;
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_heer() {
+        let heer = Conventions::new(Service::Heer);
+        assert_eq!(
+            heer.encode("Angriff auf SEBEZ um 1830 Uhr.").unwrap(),
+            "ANGRIFFXAUFXSEBEZXSEBEZXUMXEINSAQTDREINULLXUHRX"
+        );
+        assert_eq!(heer.encode("Nächste Straße, 2 Züge").unwrap(), "NAEQSTEXSTRASZEXZWOXZUEGE");
+        assert_eq!(heer.encode("Zug 3 → Nord"), Err(ConventionError::UnsupportedChar('→')));
+    }
+
+    #[test]
+    fn test_decode_operation_barbarossa() {
+        let decrypt = "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAXNORDWESTLXSEBEZXSEBEZXUAFFLIEGERSTRASZERIQTUNGXDUBROWKIXDUBROWKIXOPOTSCHKAXOPOTSCHKAXUMXEINSAQTDREINULLXUHRANGETRETENXANGRIFFXINFXRGTX";
+        assert_eq!(
+            Conventions::new(Service::Heer).decode(decrypt),
+            "AUFKL ABTEILUNG VON KURTINOWA NORDWESTL SEBEZ UAFFLIEGERSTRASZERICHTUNG DUBROWKI OPOTSCHKA UM 1830 \
+             UHRANGETRETEN ANGRIFF INF RGT"
+        );
+    }
+
+    #[test]
+    fn test_kriegsmarine_digits() {
+        let marine = Conventions::new(Service::Kriegsmarine);
+        let encoded = marine.encode("U 47 auf Position 1830 West.").unwrap();
+        assert_eq!(encoded, "UXYRUYXAUFXPOSITIONXYQIEPYXWESTX");
+        assert_eq!(marine.decode(&encoded), "U 47 AUF POSITION 1830 WEST");
+        assert_eq!(marine.decode("YACHTXYQY"), "YACHT 1");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let heer = Conventions::new(Service::Heer);
+        let text = "Feind bei KIEW mit 250 Panzern. Zweites Regiment folgt";
+        assert_eq!(
+            heer.decode(&heer.encode(text).unwrap()),
+            "FEIND BEI KIEW MIT 250 PANZERN ZWEITES REGIMENT FOLGT"
+        );
+    }
+}
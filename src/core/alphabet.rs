@@ -1,22 +1,44 @@
-use std::ops::Add;
-use std::ops::AddAssign;
-use std::ops::Rem;
-use std::ops::Sub;
-use std::ops::SubAssign;
+use core::ops::Add;
+use core::ops::AddAssign;
+use core::ops::Rem;
+use core::ops::Sub;
+use core::ops::SubAssign;
 
 /// Alphabet for our Enigma machine
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct EnigmaAlphabet(u8);
 
 impl EnigmaAlphabet {
+    /// Create a letter from its index in the alphabet (`0` is `A`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not in `0..26`.
     #[inline]
-    pub fn to_u8(&self) -> u8 {
+    pub const fn new(value: u8) -> Self {
+        assert!(value < 26, "Invalid letter value");
+        Self(value)
+    }
+
+    /// Create a letter from an uppercase ASCII byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte` is not in `b'A'..=b'Z'`.
+    #[inline]
+    pub const fn from_ascii(byte: u8) -> Self {
+        assert!(byte.is_ascii_uppercase(), "Invalid letter");
+        Self(byte - b'A')
+    }
+
+    #[inline]
+    pub const fn to_u8(&self) -> u8 {
         self.0
     }
 
     #[inline]
-    pub fn to_char(&self) -> char {
-        (self.to_u8() + b'A') as char
+    pub const fn to_char(&self) -> char {
+        (self.0 + b'A') as char
     }
 }
 
@@ -40,6 +62,7 @@ impl From<EnigmaAlphabet> for char {
     }
 }
 
+#[cfg(feature = "std")]
 impl From<EnigmaAlphabet> for String {
     #[inline]
     fn from(value: EnigmaAlphabet) -> Self {
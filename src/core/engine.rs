@@ -1,33 +1,82 @@
 use super::alphabet::EnigmaAlphabet;
 use super::decoder::Decoder;
 use super::encoder::Encoder;
+use super::observer::Observer;
 use super::parts::plugboard::Plugboard;
 use super::parts::reflector::Reflector;
 use super::parts::rotor::Rotor;
 
-pub struct Enigma<const N: usize> {
+#[derive(Debug, Clone)]
+pub struct Enigma<O: Observer = ()> {
     rotors: [Rotor; 3],
     reflector: Reflector,
-    plugboard: Plugboard<N>,
+    plugboard: Plugboard,
+    observer: O,
 }
 
-impl<const N: usize> Enigma<N> {
-    pub fn new(reflector: Reflector, rotors: [Rotor; 3], plugboard: Plugboard<N>) -> Self {
-        Self { rotors, reflector, plugboard }
+impl Enigma {
+    pub fn new(reflector: Reflector, rotors: [Rotor; 3], plugboard: Plugboard) -> Self {
+        Self { rotors, reflector, plugboard, observer: () }
     }
+}
 
-    pub fn encode<I>(&mut self, input: I) -> EnigmaAlphabet
+impl<O: Observer> Enigma<O> {
+    /// Replace the observer that is notified about stepping and lamps
+    pub fn with_observer<P: Observer>(self, observer: P) -> Enigma<P> {
+        let Self { rotors, reflector, plugboard, .. } = self;
+        Enigma { rotors, reflector, plugboard, observer }
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Letters shown in the windows, left to right
+    pub fn positions(&self) -> [EnigmaAlphabet; 3] {
+        self.rotors.each_ref().map(Rotor::position)
+    }
+
+    /// Turn the rotors to the given window letters, left to right
+    pub fn set_positions<I>(&mut self, positions: [I; 3])
     where
         I: Into<EnigmaAlphabet>,
     {
-        // turn rotors on press
-        for i in (0..3).rev() {
-            if !self.rotors[i].turn() {
-                break;
-            }
+        for (rotor, position) in self.rotors.iter_mut().zip(positions) {
+            rotor.set(position);
         }
+    }
+
+    /// Ring settings, left to right
+    pub fn rings(&self) -> [u8; 3] {
+        self.rotors.each_ref().map(Rotor::ring)
+    }
+
+    pub fn set_rings(&mut self, rings: [u8; 3]) {
+        for (rotor, ring) in self.rotors.iter_mut().zip(rings) {
+            rotor.set_ring(ring);
+        }
+    }
+
+    pub fn encode<I>(&mut self, input: I) -> EnigmaAlphabet
+    where
+        I: Into<EnigmaAlphabet>,
+    {
+        self.step();
 
         let input: EnigmaAlphabet = input.into();
+        let x = self.scramble(input);
+        assert_eq!(input, self.scramble(x), "encode fail");
+
+        self.observer.lamp(input, x);
+        x
+    }
+
+    /// Letter lit for `input` at the current rotor positions, without stepping
+    fn scramble(&self, input: EnigmaAlphabet) -> EnigmaAlphabet {
         let x = self.plugboard.encode(input);
         let x = self.rotors[2].encode(x);
         let x = self.rotors[1].encode(x);
@@ -37,25 +86,54 @@ impl<const N: usize> Enigma<N> {
         let x = self.rotors[0].decode(x);
         let x = self.rotors[1].decode(x);
         let x = self.rotors[2].decode(x);
-        let x = self.plugboard.encode(x);
+        self.plugboard.encode(x)
+    }
 
-        assert_eq!(
-            input,
-            {
-                let y = self.plugboard.encode(x);
-                let y = self.rotors[2].encode(y);
-                let y = self.rotors[1].encode(y);
-                let y = self.rotors[0].encode(y);
-                let y = self.reflector.encode(y);
-                let y = self.rotors[0].decode(y);
-                let y = self.rotors[1].decode(y);
-                let y = self.rotors[2].decode(y);
-                self.plugboard.encode(y)
-            },
-            "encode fail"
-        );
+    /// Substitution of all 26 letters at the current rotor positions, without
+    /// stepping, for the analysis tools
+    #[cfg(feature = "std")]
+    pub(crate) fn permutation(&self) -> [EnigmaAlphabet; 26] {
+        let mut permutation = [None; 26];
+        for i in 0..26 {
+            if permutation[i].is_none() {
+                let input = EnigmaAlphabet::new(i as u8);
+                let output = self.scramble(input);
+                // the substitution is its own inverse
+                permutation[i] = Some(output);
+                permutation[usize::from(output)] = Some(input);
+            }
+        }
+        permutation.map(|letter| letter.expect("every letter is paired"))
+    }
 
-        x
+    /// Turn rotors on key press. The right rotor always steps, the middle rotor
+    /// steps when the right rotor or the middle rotor itself is at a notch (the
+    /// latter is the double step), and the left rotor steps with the middle
+    /// rotor's notch.
+    pub(crate) fn step(&mut self) {
+        let right_at_notch = self.rotors[2].at_turnover();
+        let middle_at_notch = self.rotors[1].at_turnover();
+
+        if right_at_notch {
+            self.observer.turnover(2);
+        }
+        if middle_at_notch {
+            self.observer.turnover(1);
+            self.observer.double_step();
+        }
+
+        self.turn(2);
+        if right_at_notch || middle_at_notch {
+            self.turn(1);
+        }
+        if middle_at_notch {
+            self.turn(0);
+        }
+    }
+
+    fn turn(&mut self, slot: usize) {
+        self.rotors[slot].turn();
+        self.observer.rotor_stepped(slot, self.rotors[slot].position());
     }
 }
 
@@ -71,21 +149,15 @@ mod tests {
         // rotor II:  AJDKSIRUXBLHWTMCQGZNPYFVOE
         // rotor III: BDFHJLCPRTXVZNYEIWGAKMUSQO
 
-        let mut enigma_m3 = Enigma::new(
-            reflector_b(),
-            [rotor_i(0), rotor_ii(0), rotor_iii(0)],
-            Plugboard::<0>::new([]),
-        );
+        let mut enigma_m3 =
+            Enigma::new(reflector_b(), [rotor_i(0), rotor_ii(0), rotor_iii(0)], Plugboard::new([]));
 
         let input = 'G';
         let output = enigma_m3.encode(input);
         assert_eq!(output, 'P'.into());
 
-        let mut enigma_m3 = Enigma::new(
-            reflector_b(),
-            [rotor_i(0), rotor_ii(0), rotor_iii(0)],
-            Plugboard::<0>::new([]),
-        );
+        let mut enigma_m3 =
+            Enigma::new(reflector_b(), [rotor_i(0), rotor_ii(0), rotor_iii(0)], Plugboard::new([]));
 
         let input = 'P';
         let output = enigma_m3.encode(input);
@@ -97,7 +169,7 @@ mod tests {
         let mut enigma_m3 = Enigma::new(
             reflector_b(),
             [rotor_ii(25), rotor_ii(0), rotor_ii(0)],
-            Plugboard::<0>::new([]),
+            Plugboard::new([]),
         );
 
         // enigma_m3.rotors[2].set_ring(1);
@@ -220,4 +292,103 @@ mod tests {
 
         assert_eq!(vec![EnigmaAlphabet::from(0); LEN], answer_backwards);
     }
+
+    #[test]
+    fn enigma_positions() {
+        let mut enigma = Enigma::new(
+            reflector_b(),
+            [rotor_i('A'), rotor_ii('D'), rotor_iii('U')],
+            Plugboard::default(),
+        );
+        enigma.encode('A');
+        assert_eq!(enigma.positions(), ['A'.into(), 'D'.into(), 'V'.into()]);
+
+        enigma.set_positions(['Q', 'E', 'V']);
+        assert_eq!(enigma.positions(), ['Q'.into(), 'E'.into(), 'V'.into()]);
+    }
+
+    #[test]
+    fn enigma_double_step() {
+        let mut enigma = Enigma::new(
+            reflector_b(),
+            [rotor_i('A'), rotor_ii('D'), rotor_iii('U')],
+            Plugboard::default(),
+        );
+
+        let mut windows = Vec::new();
+        for _ in 0..4 {
+            enigma.encode('A');
+            windows.push(enigma.positions().map(char::from).iter().collect::<String>());
+        }
+        assert_eq!(windows, ["ADV", "AEW", "BFX", "BFY"]);
+    }
+
+    #[test]
+    fn enigma_double_step_known_answers() {
+        // rotors I II III, reflector B, rings at A, no plugs
+        for (start, plaintext, ciphertext) in [
+            (['A', 'A', 'A'], "AAAAA", "BDZGO"),
+            (['A', 'D', 'U'], "AAAAAAAAAA", "EQIBMGFJBW"),
+            (['A', 'D', 'U'], "HELLOWORLD", "IBXXXNVDFL"),
+        ] {
+            let mut enigma = Enigma::new(
+                reflector_b(),
+                [rotor_i(start[0]), rotor_ii(start[1]), rotor_iii(start[2])],
+                Plugboard::default(),
+            );
+            let output: String = plaintext.chars().map(|ch| enigma.encode(ch).to_char()).collect();
+            assert_eq!(output, ciphertext);
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Observer for Recorder {
+        fn rotor_stepped(&mut self, slot: usize, position: EnigmaAlphabet) {
+            self.events.push(format!("step {slot} {}", position.to_char()));
+        }
+
+        fn turnover(&mut self, slot: usize) {
+            self.events.push(format!("turnover {slot}"));
+        }
+
+        fn double_step(&mut self) {
+            self.events.push("double step".into());
+        }
+
+        fn lamp(&mut self, input: EnigmaAlphabet, output: EnigmaAlphabet) {
+            self.events.push(format!("lamp {} {}", input.to_char(), output.to_char()));
+        }
+    }
+
+    #[test]
+    fn enigma_observer() {
+        let mut enigma = Enigma::new(
+            reflector_b(),
+            [rotor_i('A'), rotor_ii('E'), rotor_iii('W')],
+            Plugboard::default(),
+        )
+        .with_observer(Recorder::default());
+
+        let output = enigma.encode('A');
+        assert_eq!(
+            enigma.observer().events,
+            [
+                "turnover 1",
+                "double step",
+                "step 2 X",
+                "step 1 F",
+                "step 0 B",
+                &format!("lamp A {}", output.to_char()),
+            ]
+        );
+
+        enigma.observer_mut().events.clear();
+        enigma.set_positions(['A', 'A', 'V']);
+        enigma.encode('A');
+        assert_eq!(enigma.observer().events[..3], ["turnover 2", "step 2 W", "step 1 B"]);
+    }
 }
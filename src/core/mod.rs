@@ -2,4 +2,5 @@ pub mod alphabet;
 pub mod decoder;
 pub mod encoder;
 pub mod engine;
+pub mod observer;
 pub mod parts;
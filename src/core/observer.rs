@@ -0,0 +1,57 @@
+use super::alphabet::EnigmaAlphabet;
+
+/// Receives events from an [`Enigma`](super::engine::Enigma) as keys are
+/// pressed.
+///
+/// Every method has an empty default body, so an observer only implements the
+/// events it cares about. Rotor slots are numbered left to right, `0` being the
+/// leftmost rotor. The unit type is the default observer and compiles down to
+/// nothing.
+pub trait Observer {
+    /// The rotor in `slot` stepped and now shows `position` in its window
+    #[inline]
+    fn rotor_stepped(&mut self, slot: usize, position: EnigmaAlphabet) {
+        let _ = (slot, position);
+    }
+
+    /// The rotor in `slot` was at a notch and carried its left neighbour
+    #[inline]
+    fn turnover(&mut self, slot: usize) {
+        let _ = slot;
+    }
+
+    /// The middle rotor was at its own notch and stepped together with the
+    /// left rotor
+    #[inline]
+    fn double_step(&mut self) {}
+
+    /// `input` was pressed and lit the `output` lamp
+    #[inline]
+    fn lamp(&mut self, input: EnigmaAlphabet, output: EnigmaAlphabet) {
+        let _ = (input, output);
+    }
+}
+
+impl Observer for () {}
+
+impl<O: Observer + ?Sized> Observer for &mut O {
+    #[inline]
+    fn rotor_stepped(&mut self, slot: usize, position: EnigmaAlphabet) {
+        (**self).rotor_stepped(slot, position)
+    }
+
+    #[inline]
+    fn turnover(&mut self, slot: usize) {
+        (**self).turnover(slot)
+    }
+
+    #[inline]
+    fn double_step(&mut self) {
+        (**self).double_step()
+    }
+
+    #[inline]
+    fn lamp(&mut self, input: EnigmaAlphabet, output: EnigmaAlphabet) {
+        (**self).lamp(input, output)
+    }
+}
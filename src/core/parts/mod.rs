@@ -1,4 +1,5 @@
 pub mod plugboard;
 pub mod reflector;
 pub mod rotor;
+pub mod turnover;
 pub mod wiring;
@@ -1,45 +1,64 @@
 use crate::core::alphabet::EnigmaAlphabet;
 use crate::core::encoder::Encoder;
 
-pub struct Plugboard<const N: usize = 10> {
-    pairs: [(EnigmaAlphabet, EnigmaAlphabet); N],
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Plugboard {
+    outputs: [EnigmaAlphabet; 26],
 }
 
-impl<const N: usize> Plugboard<N> {
+impl Plugboard {
     /// Create a new Plugboard with the given pairs of letters.
     ///
     /// # Panics
     ///
     /// Panics if any letter appears twice in the pairs.
-    pub fn new(pairs: [(EnigmaAlphabet, EnigmaAlphabet); N]) -> Self {
-        let mut letter_set = std::collections::HashSet::new();
-        for (a, b) in pairs.iter() {
-            assert!(!letter_set.contains(a), "{:?} appears twice in pairs", a);
-            assert!(!letter_set.contains(b), "{:?} appears twice in pairs", b);
-            letter_set.insert(a);
-            letter_set.insert(b);
+    pub fn new<const N: usize>(pairs: [(EnigmaAlphabet, EnigmaAlphabet); N]) -> Self {
+        Self::from_pairs(pairs)
+    }
+
+    /// Create a new Plugboard from any number of pairs of letters, e.g. when
+    /// the cable count is only known at runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any letter appears twice in the pairs.
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (EnigmaAlphabet, EnigmaAlphabet)>) -> Self {
+        let mut outputs = core::array::from_fn(|i| EnigmaAlphabet::new(i as u8));
+        let mut seen = 0u32;
+        for (a, b) in pairs {
+            assert!(seen & 1 << a.to_u8() == 0, "{:?} appears twice in pairs", a);
+            assert!(seen & 1 << b.to_u8() == 0, "{:?} appears twice in pairs", b);
+            seen |= 1 << a.to_u8() | 1 << b.to_u8();
+            outputs[usize::from(a)] = b;
+            outputs[usize::from(b)] = a;
         }
-        Self { pairs }
+        Self { outputs }
+    }
+
+    /// Plugged pairs, each with the alphabetically lower letter first
+    pub fn pairs(&self) -> impl Iterator<Item = (EnigmaAlphabet, EnigmaAlphabet)> + '_ {
+        self.outputs
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| (EnigmaAlphabet::new(i as u8), b))
+            .filter(|(a, b)| a.to_u8() < b.to_u8())
     }
 }
 
-impl<const N: usize> Encoder for Plugboard<N> {
+impl Default for Plugboard {
+    fn default() -> Self {
+        Self::new([])
+    }
+}
+
+impl Encoder for Plugboard {
     type Letter = EnigmaAlphabet;
 
     fn encode<I>(&self, input: I) -> Self::Letter
     where
         I: Into<Self::Letter>,
     {
-        let input = input.into();
-        for (a, b) in self.pairs.iter() {
-            if input == *a {
-                return *b;
-            }
-            if input == *b {
-                return *a;
-            }
-        }
-        input
+        self.outputs[usize::from(input.into())]
     }
 }
 
@@ -55,6 +74,18 @@ mod tests {
         assert_eq!(encoded, 1.into());
     }
 
+    #[test]
+    fn test_plugboard_pairs() {
+        let plugboard = Plugboard::from_pairs([('Z'.into(), 'A'.into()), ('C'.into(), 'B'.into())]);
+
+        assert_eq!(plugboard.encode('A'), 'Z'.into());
+        assert_eq!(plugboard.encode('D'), 'D'.into());
+        assert_eq!(
+            plugboard.pairs().collect::<Vec<_>>(),
+            [('A'.into(), 'Z'.into()), ('B'.into(), 'C'.into())]
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_plugboard_duplicate_pair() {
@@ -2,18 +2,33 @@ use super::wiring::Wiring;
 use crate::core::alphabet::EnigmaAlphabet;
 use crate::core::encoder::Encoder;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Reflector {
     wiring: Wiring,
 }
 
 impl Reflector {
     pub fn new(wiring: impl Into<Wiring>) -> Self {
-        let wiring = wiring.into();
-        assert!(
-            ('A'..='Z')
-                .all(|x| wiring.left_to_right_wire(wiring.right_to_left_wire(x)) == x.into()),
-            "reflector should be a mirror (e.i. permutation where cycles are only len 2) "
-        );
+        Self::from_wiring(wiring.into())
+    }
+
+    /// Create a reflector, usable in `const` context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the wiring is not a mirror. In `const` context this is a
+    /// compile error.
+    pub const fn from_wiring(wiring: Wiring) -> Self {
+        let mut i = 0;
+        while i < 26 {
+            let x = EnigmaAlphabet::new(i);
+            let y = wiring.left_to_right(x);
+            assert!(
+                y.to_u8() != x.to_u8() && wiring.left_to_right(y).to_u8() == x.to_u8(),
+                "reflector should be a mirror (e.i. permutation where cycles are only len 2) "
+            );
+            i += 1;
+        }
         Self { wiring }
     }
 }
@@ -59,4 +74,10 @@ mod tests {
     fn test_reflector_invalid() {
         let _ = Reflector::new("ABCDEFGHIJKLMNOPQRSTUVWXAZ");
     }
+
+    #[test]
+    #[should_panic]
+    fn test_reflector_not_a_mirror() {
+        let _ = Reflector::new("BCDEFGHIJKLMNOPQRSTUVWXYZA");
+    }
 }
@@ -1,6 +1,6 @@
-use std::collections::HashSet;
-use std::ops::Sub;
+use core::ops::Sub;
 
+use super::turnover::Turnover;
 use super::wiring::Wiring;
 use crate::core::alphabet::EnigmaAlphabet;
 use crate::core::decoder::Decoder;
@@ -10,7 +10,7 @@ use crate::core::encoder::Encoder;
 pub struct Rotor {
     wiring: Wiring,
     ring: u8,
-    turnover: HashSet<EnigmaAlphabet>,
+    turnover: Turnover,
     position: EnigmaAlphabet,
 }
 
@@ -19,10 +19,13 @@ impl Rotor {
         wiring: impl Into<Wiring>,
         trunover: impl IntoIterator<Item = EnigmaAlphabet>,
     ) -> Self {
-        let wiring = wiring.into();
-        let turnover = trunover.into_iter().collect();
-        let position = 0.into();
-        Self { wiring, ring: 0u8, turnover, position }
+        Self::from_parts(wiring.into(), trunover.into_iter().collect())
+    }
+
+    /// Create a rotor at position `A` with ring setting `A`, usable in `const`
+    /// context.
+    pub const fn from_parts(wiring: Wiring, turnover: Turnover) -> Self {
+        Self { wiring, ring: 0, turnover, position: EnigmaAlphabet::new(0) }
     }
 
     pub fn with_position(
@@ -35,12 +38,29 @@ impl Rotor {
         rotor
     }
 
+    /// Whether the rotor shows a notch letter, i.e. whether the next
+    /// [`turn`](Self::turn) carries its left neighbour
+    #[inline]
+    pub fn at_turnover(&self) -> bool {
+        self.turnover.contains(self.position)
+    }
+
     pub fn turn(&mut self) -> bool {
-        let is_turnover = self.turnover.contains(&self.position);
-        self.position = self.position + 1;
+        let is_turnover = self.at_turnover();
+        self.position += 1;
         is_turnover
     }
 
+    #[inline]
+    pub fn position(&self) -> EnigmaAlphabet {
+        self.position
+    }
+
+    #[inline]
+    pub fn ring(&self) -> u8 {
+        self.ring
+    }
+
     pub fn set(&mut self, position: impl Into<EnigmaAlphabet>) {
         self.position = position.into();
     }
@@ -135,6 +155,20 @@ mod tests {
         assert_eq!(rotor.decode('B'), 'X'.into());
     }
 
+    #[test]
+    fn test_rotor_two_notches() {
+        let mut rotor =
+            Rotor::with_position("JPGVOUMFYQBENHZRDKASXLICTW", "ZM".chars().map(Into::into), 'L');
+
+        assert!(!rotor.turn());
+        assert!(rotor.turn());
+        assert!(!rotor.turn());
+
+        rotor.set('Z');
+        assert!(rotor.turn());
+        assert_eq!(rotor.position, 'A'.into());
+    }
+
     #[test]
     #[should_panic]
     fn test_rotor_invalid() {
@@ -0,0 +1,50 @@
+use crate::core::alphabet::EnigmaAlphabet;
+
+/// Set of letters at which a rotor carries a notch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Turnover(u32);
+
+impl Turnover {
+    /// Create a turnover set from its notch letters, e.g. `"ZM"`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `letters` contains anything but `A..=Z`. In `const` context
+    /// this is a compile error.
+    pub const fn from_letters(letters: &str) -> Self {
+        let bytes = letters.as_bytes();
+        let mut mask = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            mask |= 1 << EnigmaAlphabet::from_ascii(bytes[i]).to_u8();
+            i += 1;
+        }
+        Self(mask)
+    }
+
+    #[inline]
+    pub const fn contains(&self, letter: EnigmaAlphabet) -> bool {
+        self.0 & 1 << letter.to_u8() != 0
+    }
+}
+
+impl FromIterator<EnigmaAlphabet> for Turnover {
+    fn from_iter<T: IntoIterator<Item = EnigmaAlphabet>>(iter: T) -> Self {
+        Self(iter.into_iter().fold(0, |mask, letter| mask | 1 << letter.to_u8()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_turnover() {
+        const ZM: Turnover = Turnover::from_letters("ZM");
+
+        assert!(ZM.contains('Z'.into()));
+        assert!(ZM.contains('M'.into()));
+        assert!(!ZM.contains('A'.into()));
+        assert_eq!(ZM, "MZ".chars().map(EnigmaAlphabet::from).collect());
+    }
+}
@@ -1,27 +1,55 @@
-use std::collections::HashSet;
-
 use crate::core::alphabet::EnigmaAlphabet;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Wiring {
     outputs: [EnigmaAlphabet; 26],
     inverts: [EnigmaAlphabet; 26],
 }
 
 impl Wiring {
-    pub fn new(outputs: [EnigmaAlphabet; 26]) -> Self {
-        let set: HashSet<EnigmaAlphabet> = outputs.into_iter().collect();
-        assert!(set.len() == 26, "Wiring must contain 26 unique letters");
-
-        let mut inverts = [0.into(); 26];
-        for i in 0u8..26 {
-            let l: usize = u8::from(outputs[i as usize]).into();
-            inverts[l] = i.into();
+    /// Create a wiring where letter `i` is wired to `outputs[i]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `outputs` is not a permutation of the alphabet. In `const`
+    /// context this is a compile error.
+    pub const fn new(outputs: [EnigmaAlphabet; 26]) -> Self {
+        let mut seen = 0u32;
+        let mut inverts = [EnigmaAlphabet::new(0); 26];
+        let mut i = 0;
+        while i < 26 {
+            let l = outputs[i].to_u8();
+            seen |= 1 << l;
+            inverts[l as usize] = EnigmaAlphabet::new(i as u8);
+            i += 1;
         }
+        assert!(seen == (1 << 26) - 1, "Wiring must contain 26 unique letters");
 
         Self { outputs, inverts }
     }
 
+    /// Create a wiring from its 26 output letters, e.g. `"EKMFLGDQVZNTOWYHXUSPAIBRCJ"`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `letters` is not a permutation of `A..=Z`. In `const` context
+    /// this is a compile error.
+    pub const fn from_letters(letters: &str) -> Self {
+        let bytes = letters.as_bytes();
+        assert!(bytes.len() == 26, "Wiring string must contain 26 characters");
+        let mut outputs = [EnigmaAlphabet::new(0); 26];
+        let mut i = 0;
+        while i < 26 {
+            outputs[i] = EnigmaAlphabet::from_ascii(bytes[i]);
+            i += 1;
+        }
+        Self::new(outputs)
+    }
+
+    pub(crate) const fn left_to_right(&self, input: EnigmaAlphabet) -> EnigmaAlphabet {
+        self.outputs[input.to_u8() as usize]
+    }
+
     pub(crate) fn left_to_right_wire(&self, input: impl Into<EnigmaAlphabet>) -> EnigmaAlphabet {
         self.outputs[usize::from(input.into().to_u8())]
     }
@@ -53,3 +81,23 @@ impl From<&str> for Wiring {
         Self::new(outputs_array)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROTOR_I: Wiring = Wiring::from_letters("EKMFLGDQVZNTOWYHXUSPAIBRCJ");
+
+    #[test]
+    fn test_const_wiring() {
+        assert_eq!(ROTOR_I, Wiring::from("EKMFLGDQVZNTOWYHXUSPAIBRCJ"));
+        assert_eq!(ROTOR_I.left_to_right_wire('A'), 'E'.into());
+        assert_eq!(ROTOR_I.right_to_left_wire('E'), 'A'.into());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_wiring_duplicate_letter() {
+        let _ = Wiring::from_letters("EKMFLGDQVZNTOWYHXUSPAIBRCC");
+    }
+}
@@ -29,47 +29,103 @@
 use crate::core::alphabet::EnigmaAlphabet;
 use crate::core::parts::reflector::Reflector;
 use crate::core::parts::rotor::Rotor;
+use crate::core::parts::turnover::Turnover;
+use crate::core::parts::wiring::Wiring;
 
-pub const ROTOR_I: (&str, &str) = ("EKMFLGDQVZNTOWYHXUSPAIBRCJ", "Q");
-pub const ROTOR_II: (&str, &str) = ("AJDKSIRUXBLHWTMCQGZNPYFVOE", "E");
-pub const ROTOR_III: (&str, &str) = ("BDFHJLCPRTXVZNYEIWGAKMUSQO", "V");
-pub const ROTOR_IV: (&str, &str) = ("ESOVPZJAYQUIRHXLNFTGKDCMWB", "J");
-pub const ROTOR_V: (&str, &str) = ("VZBRGITYUPSDNHLXAWMJQOFECK", "Z");
-pub const ROTOR_VI: (&str, &str) = ("JPGVOUMFYQBENHZRDKASXLICTW", "ZM");
-pub const ROTOR_VII: (&str, &str) = ("NZJHGRCXMYSWBOUFAIVLPEKQDT", "ZM");
-pub const ROTOR_VIII: (&str, &str) = ("FKQHTLXOCBJSPDZRAMEWNIUYGV", "ZM");
-pub const UWK_A: &str = "EJMZALYXVBWFCRQUONTSPIKHGD";
-pub const UKW_B: &str = "YRUHQSLDPXNGOKMIEBFZCWVJAT";
-pub const UKW_C: &str = "FVPJIAOYEDRZXWGCTKUQSBNMHL";
+pub const ROTOR_I: Rotor = rotor("EKMFLGDQVZNTOWYHXUSPAIBRCJ", "Q");
+pub const ROTOR_II: Rotor = rotor("AJDKSIRUXBLHWTMCQGZNPYFVOE", "E");
+pub const ROTOR_III: Rotor = rotor("BDFHJLCPRTXVZNYEIWGAKMUSQO", "V");
+pub const ROTOR_IV: Rotor = rotor("ESOVPZJAYQUIRHXLNFTGKDCMWB", "J");
+pub const ROTOR_V: Rotor = rotor("VZBRGITYUPSDNHLXAWMJQOFECK", "Z");
+pub const ROTOR_VI: Rotor = rotor("JPGVOUMFYQBENHZRDKASXLICTW", "ZM");
+pub const ROTOR_VII: Rotor = rotor("NZJHGRCXMYSWBOUFAIVLPEKQDT", "ZM");
+pub const ROTOR_VIII: Rotor = rotor("FKQHTLXOCBJSPDZRAMEWNIUYGV", "ZM");
+pub const UKW_A: Reflector = reflector("EJMZALYXVBWFCRQUONTSPIKHGD");
+pub const UKW_B: Reflector = reflector("YRUHQSLDPXNGOKMIEBFZCWVJAT");
+pub const UKW_C: Reflector = reflector("FVPJIAOYEDRZXWGCTKUQSBNMHL");
+
+/// Former, misspelt name of [`UKW_A`]
+#[deprecated(note = "renamed to `UKW_A`")]
+pub const UWK_A: Reflector = UKW_A;
+
+const fn rotor(wiring: &str, turnover: &str) -> Rotor {
+    Rotor::from_parts(Wiring::from_letters(wiring), Turnover::from_letters(turnover))
+}
+
+const fn reflector(wiring: &str) -> Reflector {
+    Reflector::from_wiring(Wiring::from_letters(wiring))
+}
+
+fn at(mut rotor: Rotor, position: impl Into<EnigmaAlphabet>) -> Rotor {
+    rotor.set(position);
+    rotor
+}
 
 pub fn rotor_i(position: impl Into<EnigmaAlphabet>) -> Rotor {
-    Rotor::with_position(ROTOR_I.0, ROTOR_I.1.chars().map(Into::into), position)
+    at(ROTOR_I, position)
 }
 
 pub fn rotor_ii(position: impl Into<EnigmaAlphabet>) -> Rotor {
-    Rotor::with_position(ROTOR_II.0, ROTOR_II.1.chars().map(Into::into), position)
+    at(ROTOR_II, position)
 }
 
 pub fn rotor_iii(position: impl Into<EnigmaAlphabet>) -> Rotor {
-    Rotor::with_position(ROTOR_III.0, ROTOR_III.1.chars().map(Into::into), position)
+    at(ROTOR_III, position)
 }
 
 pub fn rotor_iv(position: impl Into<EnigmaAlphabet>) -> Rotor {
-    Rotor::with_position(ROTOR_IV.0, ROTOR_IV.1.chars().map(Into::into), position)
+    at(ROTOR_IV, position)
 }
 
 pub fn rotor_v(position: impl Into<EnigmaAlphabet>) -> Rotor {
-    Rotor::with_position(ROTOR_V.0, ROTOR_V.1.chars().map(Into::into), position)
+    at(ROTOR_V, position)
+}
+
+pub fn rotor_vi(position: impl Into<EnigmaAlphabet>) -> Rotor {
+    at(ROTOR_VI, position)
+}
+
+pub fn rotor_vii(position: impl Into<EnigmaAlphabet>) -> Rotor {
+    at(ROTOR_VII, position)
+}
+
+pub fn rotor_viii(position: impl Into<EnigmaAlphabet>) -> Rotor {
+    at(ROTOR_VIII, position)
 }
 
 pub fn reflector_a() -> Reflector {
-    Reflector::new(UWK_A)
+    UKW_A
 }
 
 pub fn reflector_b() -> Reflector {
-    Reflector::new(UKW_B)
+    UKW_B
 }
 
 pub fn reflector_c() -> Reflector {
-    Reflector::new(UKW_C)
+    UKW_C
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::encoder::Encoder;
+
+    #[test]
+    fn test_const_catalogue() {
+        let runtime = Rotor::new("EKMFLGDQVZNTOWYHXUSPAIBRCJ", "Q".chars().map(Into::into));
+        for letter in 0u8..26 {
+            assert_eq!(rotor_i(0).encode(letter), runtime.encode(letter));
+        }
+
+        assert_eq!(UKW_B.encode('A'), 'Y'.into());
+        assert_eq!(reflector_c().encode('A'), 'F'.into());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_alias() {
+        for letter in 0u8..26 {
+            assert_eq!(UWK_A.encode(letter), UKW_A.encode(letter));
+        }
+    }
 }
@@ -1 +1,2 @@
 pub mod enigma_m3;
+pub mod model;
@@ -0,0 +1,142 @@
+//! Identities of the catalogued parts and the machine models they fit.
+
+use core::fmt;
+use core::str::FromStr;
+
+use super::enigma_m3::*;
+use crate::core::parts::reflector::Reflector;
+use crate::core::parts::rotor::Rotor;
+
+/// Rotor (Walze) of the Enigma I / M3 catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RotorId {
+    I,
+    II,
+    III,
+    IV,
+    V,
+    VI,
+    VII,
+    VIII,
+}
+
+impl RotorId {
+    pub const ALL: [RotorId; 8] = [
+        RotorId::I,
+        RotorId::II,
+        RotorId::III,
+        RotorId::IV,
+        RotorId::V,
+        RotorId::VI,
+        RotorId::VII,
+        RotorId::VIII,
+    ];
+
+    /// Fresh rotor at position `A` with ring setting `A`
+    pub const fn rotor(self) -> Rotor {
+        match self {
+            RotorId::I => ROTOR_I,
+            RotorId::II => ROTOR_II,
+            RotorId::III => ROTOR_III,
+            RotorId::IV => ROTOR_IV,
+            RotorId::V => ROTOR_V,
+            RotorId::VI => ROTOR_VI,
+            RotorId::VII => ROTOR_VII,
+            RotorId::VIII => ROTOR_VIII,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            RotorId::I => "I",
+            RotorId::II => "II",
+            RotorId::III => "III",
+            RotorId::IV => "IV",
+            RotorId::V => "V",
+            RotorId::VI => "VI",
+            RotorId::VII => "VII",
+            RotorId::VIII => "VIII",
+        }
+    }
+}
+
+impl fmt::Display for RotorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for RotorId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RotorId::ALL.into_iter().find(|id| id.name() == s).ok_or(())
+    }
+}
+
+/// Reflector (Umkehrwalze) of the Enigma I / M3 catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ReflectorId {
+    A,
+    B,
+    C,
+}
+
+impl ReflectorId {
+    pub const ALL: [ReflectorId; 3] = [ReflectorId::A, ReflectorId::B, ReflectorId::C];
+
+    pub const fn reflector(self) -> Reflector {
+        match self {
+            ReflectorId::A => UKW_A,
+            ReflectorId::B => UKW_B,
+            ReflectorId::C => UKW_C,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            ReflectorId::A => "A",
+            ReflectorId::B => "B",
+            ReflectorId::C => "C",
+        }
+    }
+}
+
+impl fmt::Display for ReflectorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl FromStr for ReflectorId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReflectorId::ALL.into_iter().find(|id| id.name() == s).ok_or(())
+    }
+}
+
+/// What the machine is: the rotors and reflectors that can be fitted to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Model {
+    /// Heer and Luftwaffe: rotors I to V, UKW A (until 1937), B and C
+    EnigmaI,
+    /// Kriegsmarine: rotors I to VIII, UKW B and C
+    M3,
+}
+
+impl Model {
+    pub const fn rotors(self) -> &'static [RotorId] {
+        match self {
+            Model::EnigmaI => &[RotorId::I, RotorId::II, RotorId::III, RotorId::IV, RotorId::V],
+            Model::M3 => &RotorId::ALL,
+        }
+    }
+
+    pub const fn reflectors(self) -> &'static [ReflectorId] {
+        match self {
+            Model::EnigmaI => &ReflectorId::ALL,
+            Model::M3 => &[ReflectorId::B, ReflectorId::C],
+        }
+    }
+}
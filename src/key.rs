@@ -0,0 +1,464 @@
+//! # Keys
+//!
+//! The operator's key as plain data, apart from the machine it is set on. A
+//! daily key ([`DailyKey`]) is made of the wheel order ([`Walzenlage`]), the
+//! ring settings ([`Ringstellung`]), the plug pairs ([`Steckerverbindungen`])
+//! and, where the procedure uses one, the basic start position
+//! ([`Grundstellung`]). Each message is then sent at its own [`MessageKey`].
+//!
+//! All types print and parse in the layout of the key sheets, e.g. the daily
+//! key `B II IV V 02 21 12 AV BS CG DL FU HZ IN KM OW RX WXC`.
+
+use core::fmt;
+use core::iter::Peekable;
+use core::str::FromStr;
+use core::str::SplitWhitespace;
+
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::engine::Enigma;
+use crate::core::parts::plugboard::Plugboard;
+use crate::historical_machines::model::Model;
+use crate::historical_machines::model::ReflectorId;
+use crate::historical_machines::model::RotorId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyError {
+    MissingField,
+    UnexpectedField,
+    UnknownReflector,
+    UnknownRotor,
+    InvalidRing,
+    InvalidLetters,
+    RepeatedLetter(EnigmaAlphabet),
+    RepeatedRotor(RotorId),
+    RotorNotInModel(RotorId),
+    ReflectorNotInModel(ReflectorId),
+    /// More plug cables than the 13 pairs the alphabet allows
+    TooManyCables(usize),
+    /// A range of plug cable numbers that holds none
+    NoCables,
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::MissingField => write!(f, "missing field"),
+            KeyError::UnexpectedField => write!(f, "unexpected field"),
+            KeyError::UnknownReflector => write!(f, "unknown reflector"),
+            KeyError::UnknownRotor => write!(f, "unknown rotor"),
+            KeyError::InvalidRing => write!(f, "ring setting must be 01-26 or A-Z"),
+            KeyError::InvalidLetters => write!(f, "expected uppercase letters A-Z"),
+            KeyError::RepeatedLetter(letter) => {
+                write!(f, "{} is plugged twice", letter.to_char())
+            }
+            KeyError::RepeatedRotor(rotor) => write!(f, "rotor {rotor} is used twice"),
+            KeyError::RotorNotInModel(rotor) => {
+                write!(f, "rotor {rotor} does not fit this model")
+            }
+            KeyError::ReflectorNotInModel(reflector) => {
+                write!(f, "UKW {reflector} does not fit this model")
+            }
+            KeyError::TooManyCables(cables) => write!(f, "{cables} cables do not fit 26 letters"),
+            KeyError::NoCables => write!(f, "no number of cables to choose from"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeyError {}
+
+type Fields<'a> = Peekable<SplitWhitespace<'a>>;
+
+/// Parse a whole string with `parse`, rejecting anything left over
+fn parse_all<T>(s: &str, parse: fn(&mut Fields) -> Result<T, KeyError>) -> Result<T, KeyError> {
+    let mut fields = s.split_whitespace().peekable();
+    let value = parse(&mut fields)?;
+    match fields.next() {
+        Some(_) => Err(KeyError::UnexpectedField),
+        None => Ok(value),
+    }
+}
+
+fn parse_letters<const N: usize>(field: &str) -> Result<[EnigmaAlphabet; N], KeyError> {
+    let bytes: [u8; N] = field.as_bytes().try_into().map_err(|_| KeyError::InvalidLetters)?;
+    if !bytes.iter().all(u8::is_ascii_uppercase) {
+        return Err(KeyError::InvalidLetters);
+    }
+    Ok(bytes.map(EnigmaAlphabet::from_ascii))
+}
+
+/// Parse one ring setting, `01`-`26` or `A`-`Z`
+pub(crate) fn parse_ring(field: &str) -> Result<u8, KeyError> {
+    match field.parse::<u8>() {
+        Ok(number @ 1..=26) => Ok(number - 1),
+        Ok(_) => Err(KeyError::InvalidRing),
+        Err(_) => Ok(parse_letters::<1>(field).map_err(|_| KeyError::InvalidRing)?[0].to_u8()),
+    }
+}
+
+/// Wheel order: the reflector and the rotors left to right
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Walzenlage {
+    pub reflector: ReflectorId,
+    pub rotors: [RotorId; 3],
+}
+
+impl Walzenlage {
+    fn parse(fields: &mut Fields) -> Result<Self, KeyError> {
+        let mut next = || fields.next().ok_or(KeyError::MissingField);
+        let reflector = next()?.parse().map_err(|_| KeyError::UnknownReflector)?;
+        let mut rotors = [RotorId::I; 3];
+        for rotor in rotors.iter_mut() {
+            *rotor = next()?.parse().map_err(|_| KeyError::UnknownRotor)?;
+        }
+        Ok(Self { reflector, rotors })
+    }
+
+    /// Every wheel order that can be set up on `model`
+    pub fn all(model: Model) -> impl Iterator<Item = Self> {
+        let rotors = model.rotors();
+        model.reflectors().iter().flat_map(move |&reflector| {
+            rotors.iter().flat_map(move |&left| {
+                rotors.iter().flat_map(move |&middle| {
+                    rotors
+                        .iter()
+                        .filter(move |&&right| left != middle && middle != right && left != right)
+                        .map(move |&right| Self { reflector, rotors: [left, middle, right] })
+                })
+            })
+        })
+    }
+
+    /// Check that the wheel order can be set up on `model`
+    pub fn check(&self, model: Model) -> Result<(), KeyError> {
+        if !model.reflectors().contains(&self.reflector) {
+            return Err(KeyError::ReflectorNotInModel(self.reflector));
+        }
+        for (i, rotor) in self.rotors.iter().enumerate() {
+            if !model.rotors().contains(rotor) {
+                return Err(KeyError::RotorNotInModel(*rotor));
+            }
+            if self.rotors[..i].contains(rotor) {
+                return Err(KeyError::RepeatedRotor(*rotor));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Walzenlage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [left, middle, right] = self.rotors;
+        write!(f, "{} {left} {middle} {right}", self.reflector)
+    }
+}
+
+impl FromStr for Walzenlage {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, Self::parse)
+    }
+}
+
+/// Ring settings left to right, `0` being `01` (or `A`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Ringstellung(pub [u8; 3]);
+
+impl Ringstellung {
+    fn parse(fields: &mut Fields) -> Result<Self, KeyError> {
+        let mut rings = [0; 3];
+        for ring in rings.iter_mut() {
+            *ring = parse_ring(fields.next().ok_or(KeyError::MissingField)?)?;
+        }
+        Ok(Self(rings))
+    }
+}
+
+impl fmt::Display for Ringstellung {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [left, middle, right] = self.0.map(|ring| ring + 1);
+        write!(f, "{left:02} {middle:02} {right:02}")
+    }
+}
+
+impl FromStr for Ringstellung {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, Self::parse)
+    }
+}
+
+/// Plug pairs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Steckerverbindungen(Plugboard);
+
+impl Steckerverbindungen {
+    /// Collect plug pairs, rejecting letters that are plugged twice
+    pub fn from_pairs(
+        pairs: impl IntoIterator<Item = (EnigmaAlphabet, EnigmaAlphabet)>,
+    ) -> Result<Self, KeyError> {
+        let mut checked = [(EnigmaAlphabet::new(0), EnigmaAlphabet::new(0)); 13];
+        let mut len = 0;
+        let mut seen = 0u32;
+        for (a, b) in pairs {
+            for letter in [a, b] {
+                if seen & 1 << letter.to_u8() != 0 {
+                    return Err(KeyError::RepeatedLetter(letter));
+                }
+                seen |= 1 << letter.to_u8();
+            }
+            checked[len] = (a, b);
+            len += 1;
+        }
+        Ok(Self(Plugboard::from_pairs(checked[..len].iter().copied())))
+    }
+
+    /// Plug pairs, each with the alphabetically lower letter first
+    pub fn pairs(&self) -> impl Iterator<Item = (EnigmaAlphabet, EnigmaAlphabet)> + '_ {
+        self.0.pairs()
+    }
+
+    /// Number of plug cables
+    pub fn len(&self) -> usize {
+        self.pairs().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs().next().is_none()
+    }
+
+    fn parse(fields: &mut Fields) -> Result<Self, KeyError> {
+        let mut error = Ok(());
+        let pairs = core::iter::from_fn(|| {
+            let field = fields.next_if(|field| field.len() == 2)?;
+            parse_letters::<2>(field).map(|[a, b]| (a, b)).map_err(|e| error = Err(e)).ok()
+        });
+        let stecker = Self::from_pairs(pairs)?;
+        error.map(|()| stecker)
+    }
+}
+
+impl From<Steckerverbindungen> for Plugboard {
+    fn from(value: Steckerverbindungen) -> Self {
+        value.0
+    }
+}
+
+impl From<Plugboard> for Steckerverbindungen {
+    fn from(value: Plugboard) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Display for Steckerverbindungen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (a, b)) in self.pairs().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}{}", a.to_char(), b.to_char())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Steckerverbindungen {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, Self::parse)
+    }
+}
+
+macro_rules! window_letters {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name(pub [EnigmaAlphabet; 3]);
+
+        impl From<[EnigmaAlphabet; 3]> for $name {
+            fn from(value: [EnigmaAlphabet; 3]) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<$name> for [EnigmaAlphabet; 3] {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let [left, middle, right] = self.0.map(char::from);
+                write!(f, "{left}{middle}{right}")
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = KeyError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse_letters(s).map(Self)
+            }
+        }
+    };
+}
+
+window_letters! {
+    /// Basic start position the message key is enciphered at
+    Grundstellung
+}
+
+window_letters! {
+    /// Start position of the rotors for the body of one message
+    MessageKey
+}
+
+window_letters! {
+    /// Identification group from the Kenngruppenbuch or the key sheet
+    Kenngruppe
+}
+
+impl From<Grundstellung> for MessageKey {
+    fn from(value: Grundstellung) -> Self {
+        Self(value.0)
+    }
+}
+
+impl From<MessageKey> for Grundstellung {
+    fn from(value: MessageKey) -> Self {
+        Self(value.0)
+    }
+}
+
+/// Everything on one row of a key sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DailyKey {
+    pub walzenlage: Walzenlage,
+    pub ringstellung: Ringstellung,
+    pub steckerverbindungen: Steckerverbindungen,
+    pub grundstellung: Option<Grundstellung>,
+}
+
+impl DailyKey {
+    fn parse(fields: &mut Fields) -> Result<Self, KeyError> {
+        let walzenlage = Walzenlage::parse(fields)?;
+        let ringstellung = Ringstellung::parse(fields)?;
+        let steckerverbindungen = Steckerverbindungen::parse(fields)?;
+        let grundstellung = fields.next().map(str::parse).transpose()?;
+        Ok(Self { walzenlage, ringstellung, steckerverbindungen, grundstellung })
+    }
+}
+
+impl fmt::Display for DailyKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.walzenlage, self.ringstellung)?;
+        if !self.steckerverbindungen.is_empty() {
+            write!(f, " {}", self.steckerverbindungen)?;
+        }
+        if let Some(grundstellung) = self.grundstellung {
+            write!(f, " {grundstellung}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DailyKey {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_all(s, Self::parse)
+    }
+}
+
+impl Enigma {
+    /// Set up a `model` machine with the daily `key`. The rotors are turned to
+    /// the Grundstellung, or to `AAA` if the key has none.
+    pub fn from_key(model: Model, key: &DailyKey) -> Result<Self, KeyError> {
+        key.walzenlage.check(model)?;
+
+        let mut enigma = Enigma::new(
+            key.walzenlage.reflector.reflector(),
+            key.walzenlage.rotors.map(RotorId::rotor),
+            key.steckerverbindungen.into(),
+        );
+        enigma.set_rings(key.ringstellung.0);
+        if let Some(grundstellung) = key.grundstellung {
+            enigma.set_positions(grundstellung.0);
+        }
+        Ok(enigma)
+    }
+
+    /// Turn the rotors to the message key
+    pub fn set_message_key(&mut self, message_key: MessageKey) {
+        self.set_positions(message_key.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BARBAROSSA: &str = "B II IV V 02 21 12 AV BS CG DL FU HZ IN KM OW RX WXC";
+
+    fn encode(enigma: &mut Enigma, text: &str) -> String {
+        text.chars().map(|ch| enigma.encode(ch).to_char()).collect()
+    }
+
+    #[test]
+    fn test_daily_key_round_trip() {
+        let key: DailyKey = BARBAROSSA.parse().unwrap();
+        assert_eq!(key.walzenlage.rotors, [RotorId::II, RotorId::IV, RotorId::V]);
+        assert_eq!(key.ringstellung, Ringstellung([1, 20, 11]));
+        assert_eq!(key.steckerverbindungen.len(), 10);
+        assert_eq!(key.to_string(), BARBAROSSA);
+
+        let key: DailyKey = "C I II III A B Z".parse().unwrap();
+        assert_eq!(key.ringstellung, Ringstellung([0, 1, 25]));
+        assert_eq!(key.grundstellung, None);
+        assert_eq!(key.to_string(), "C I II III 01 02 26");
+    }
+
+    #[test]
+    fn test_key_errors() {
+        assert_eq!("B II IV".parse::<DailyKey>(), Err(KeyError::MissingField));
+        assert_eq!("B II IV IX 1 1 1".parse::<DailyKey>(), Err(KeyError::UnknownRotor));
+        assert_eq!("B II IV V 1 27 1".parse::<DailyKey>(), Err(KeyError::InvalidRing));
+        assert_eq!(
+            "AB BC".parse::<Steckerverbindungen>(),
+            Err(KeyError::RepeatedLetter('B'.into()))
+        );
+        assert_eq!("B I II III 1 1 1 AAA BBB".parse::<DailyKey>(), Err(KeyError::UnexpectedField));
+
+        let key: DailyKey = "A VI II II 1 1 1".parse().unwrap();
+        assert_eq!(
+            Enigma::from_key(Model::EnigmaI, &key).err(),
+            Some(KeyError::RotorNotInModel(RotorId::VI))
+        );
+        assert_eq!(
+            Enigma::from_key(Model::M3, &key).err(),
+            Some(KeyError::ReflectorNotInModel(ReflectorId::A))
+        );
+    }
+
+    #[test]
+    fn test_enigma_from_key() {
+        let key: DailyKey = BARBAROSSA.parse().unwrap();
+        let mut enigma = Enigma::from_key(Model::M3, &key).unwrap();
+        let message_key: MessageKey = encode(&mut enigma, "KCH").parse().unwrap();
+        assert_eq!(message_key, "BLA".parse().unwrap());
+
+        enigma.set_message_key(message_key);
+        assert_eq!(encode(&mut enigma, "EDPUDNRGYSZRCXN"), "AUFKLXABTEILUNG");
+    }
+
+    #[test]
+    fn test_all_wheel_orders() {
+        assert_eq!(Walzenlage::all(Model::EnigmaI).count(), 3 * 60);
+        assert_eq!(Walzenlage::all(Model::M3).count(), 2 * 336);
+        assert!(Walzenlage::all(Model::M3).all(|walzenlage| walzenlage.check(Model::M3).is_ok()));
+    }
+}
@@ -0,0 +1,158 @@
+//! Deterministic key sheet generator for exercises.
+//!
+//! Keys follow the rules of the period: no rotor stays in the same slot on two
+//! consecutive days, no plug joins two letters that are neighbours in the
+//! alphabet, every day uses exactly the configured number of cables, and only
+//! rotors of the chosen model are used.
+
+use super::days_in_month;
+use super::KeySheet;
+use super::KeySheetDay;
+use super::KeySheetError;
+use crate::historical_machines::model::Model;
+use crate::historical_machines::model::ReflectorId;
+use crate::historical_machines::model::RotorId;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::key::Kenngruppe;
+use crate::key::KeyError;
+use crate::key::Ringstellung;
+use crate::key::Walzenlage;
+use crate::random::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySheetOptions {
+    pub model: Model,
+    pub reflector: ReflectorId,
+    pub cables: usize,
+    /// Whether the sheet lists a Grundstellung for each day
+    pub grundstellung: bool,
+}
+
+impl KeySheetOptions {
+    /// Heer sheet after May 1940: Enigma I, UKW B, ten cables
+    pub const fn heer() -> Self {
+        Self { model: Model::EnigmaI, reflector: ReflectorId::B, cables: 10, grundstellung: false }
+    }
+
+    /// Kriegsmarine sheet: M3, UKW B, ten cables and a Grundstellung
+    pub const fn kriegsmarine() -> Self {
+        Self { model: Model::M3, reflector: ReflectorId::B, cables: 10, grundstellung: true }
+    }
+}
+
+/// Generate the key sheet for `month` (1-12) of `year`. The same seed always
+/// gives the same sheet.
+pub fn generate(
+    options: &KeySheetOptions,
+    year: u16,
+    month: u8,
+    seed: u64,
+) -> Result<KeySheet, KeySheetError> {
+    if !(1..=12).contains(&month) {
+        return Err(KeySheetError::InvalidMonth(month));
+    }
+    if options.cables > 13 {
+        return Err(KeyError::TooManyCables(options.cables).into());
+    }
+    if !options.model.reflectors().contains(&options.reflector) {
+        return Err(KeyError::ReflectorNotInModel(options.reflector).into());
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut days = Vec::new();
+    let mut previous: Option<[RotorId; 3]> = None;
+    for day in 1..=days_in_month(year, month) {
+        let rotors = rotors(&mut rng, options.model, previous);
+        previous = Some(rotors);
+        let key = DailyKey {
+            walzenlage: Walzenlage { reflector: options.reflector, rotors },
+            ringstellung: Ringstellung([0; 3].map(|_| rng.below(26) as u8)),
+            steckerverbindungen: rng.steckerverbindungen(options.cables, true),
+            grundstellung: options.grundstellung.then(|| Grundstellung(rng.letters())),
+        };
+        let kenngruppen = [0; 4].map(|_| Kenngruppe(rng.letters()));
+        days.push(KeySheetDay { day, key, kenngruppen });
+    }
+
+    Ok(KeySheet { model: options.model, reflector: options.reflector, year, month, days })
+}
+
+/// Three different rotors, none of them in the slot it had the day before
+fn rotors(rng: &mut Rng, model: Model, previous: Option<[RotorId; 3]>) -> [RotorId; 3] {
+    let mut available = model.rotors().to_vec();
+    loop {
+        rng.shuffle(&mut available);
+        let rotors = [available[0], available[1], available[2]];
+        if previous.is_none_or(|previous| (0..3).all(|slot| rotors[slot] != previous[slot])) {
+            return rotors;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_sheet_follows_rules() {
+        for options in [KeySheetOptions::heer(), KeySheetOptions::kriegsmarine()] {
+            let sheet = generate(&options, 1941, 10, 7).unwrap();
+            assert_eq!(sheet.days.len(), 31);
+            assert_eq!(sheet, generate(&options, 1941, 10, 7).unwrap());
+            assert_ne!(sheet, generate(&options, 1941, 10, 8).unwrap());
+
+            for (i, day) in sheet.days.iter().enumerate() {
+                assert_eq!(usize::from(day.day), i + 1);
+                day.key.walzenlage.check(options.model).unwrap();
+                assert_eq!(day.key.steckerverbindungen.len(), options.cables);
+                assert!(day
+                    .key
+                    .steckerverbindungen
+                    .pairs()
+                    .all(|(a, b)| b.to_u8() - a.to_u8() != 1));
+                assert_eq!(day.key.grundstellung.is_some(), options.grundstellung);
+            }
+            for pair in sheet.days.windows(2) {
+                let [yesterday, today] = [0, 1].map(|i| pair[i].key.walzenlage.rotors);
+                assert!((0..3).all(|slot| yesterday[slot] != today[slot]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_sheet() {
+        let sheet = generate(&KeySheetOptions::heer(), 1941, 2, 1).unwrap();
+        let text = sheet.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[1], "Heeres-Maschinenschlüssel / Februar 1941 / Umkehrwalze B");
+        assert_eq!(
+            lines[3],
+            "Datum | Walzenlage     | Ringstellung | Steckerverbindungen           | Kenngruppen"
+        );
+        assert_eq!(lines.len(), 5 + 28);
+        assert!(lines[5].starts_with("   28 | "));
+        assert!(lines.last().unwrap().starts_with("    1 | "));
+
+        let sheet = generate(&KeySheetOptions::kriegsmarine(), 1941, 2, 1).unwrap();
+        assert!(sheet.to_string().contains("| Grundstellung |"));
+
+        let mut options = KeySheetOptions::heer();
+        options.reflector = ReflectorId::A;
+        options.model = Model::M3;
+        assert_eq!(
+            generate(&options, 1941, 2, 1),
+            Err(KeySheetError::Key(KeyError::ReflectorNotInModel(ReflectorId::A)))
+        );
+        assert_eq!(
+            generate(&KeySheetOptions::heer(), 1941, 13, 1),
+            Err(KeySheetError::InvalidMonth(13))
+        );
+        let options = KeySheetOptions { cables: 14, ..KeySheetOptions::heer() };
+        assert_eq!(
+            generate(&options, 1941, 2, 1),
+            Err(KeySheetError::Key(KeyError::TooManyCables(14)))
+        );
+    }
+}
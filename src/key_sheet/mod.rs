@@ -0,0 +1,230 @@
+//! # Key sheets (Schlüsselblätter)
+//!
+//! A month of daily keys, one row per day, printed the way the sheets were
+//! issued: the last day of the month on top so that used days could be cut
+//! off and burnt.
+//!
+//! ```text
+//! Geheime Kommandosache!
+//! Heeres-Maschinenschlüssel / Oktober 1941 / Umkehrwalze B
+//!
+//! Datum | Walzenlage     | Ringstellung | Steckerverbindungen           | Kenngruppen
+//! ------+----------------+--------------+-------------------------------+----------------
+//!    31 | III  I    IV   | 17 04 26     | AQ BJ CS DY EH FM GX IP KN LU | ocr yhe tbw lmf
+//!    ...
+//! ```
+//!
+//! Kriegsmarine sheets ([`Model::M3`]) are titled `Marine-Schlüssel M` and
+//! carry a `Grundstellung` column before the Kenngruppen.
+
+use core::fmt;
+
+use crate::core::engine::Enigma;
+use crate::historical_machines::model::Model;
+use crate::historical_machines::model::ReflectorId;
+use crate::key::DailyKey;
+use crate::key::Kenngruppe;
+use crate::key::KeyError;
+
+pub mod generator;
+pub mod parser;
+
+const MONTHS: [&str; 12] = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+];
+
+/// Calendar date, as far as key sheets need one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        let valid = (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day);
+        valid.then_some(Self { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Number of days in `month` (1-12) of `year`
+pub fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySheetError {
+    InvalidMonth(u8),
+    Key(KeyError),
+}
+
+impl fmt::Display for KeySheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySheetError::InvalidMonth(month) => write!(f, "invalid month {month}"),
+            KeySheetError::Key(e) => write!(f, "invalid key: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for KeySheetError {}
+
+impl From<KeyError> for KeySheetError {
+    fn from(value: KeyError) -> Self {
+        KeySheetError::Key(value)
+    }
+}
+
+/// One row of a key sheet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySheetDay {
+    pub day: u8,
+    pub key: DailyKey,
+    pub kenngruppen: [Kenngruppe; 4],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySheet {
+    pub model: Model,
+    pub reflector: ReflectorId,
+    pub year: u16,
+    pub month: u8,
+    /// Rows in calendar order
+    pub days: Vec<KeySheetDay>,
+}
+
+impl KeySheet {
+    fn title(model: Model) -> &'static str {
+        match model {
+            Model::EnigmaI => "Heeres-Maschinenschlüssel",
+            Model::M3 => "Marine-Schlüssel M",
+        }
+    }
+
+    /// Every daily key with its date, in calendar order
+    pub fn keys(&self) -> impl Iterator<Item = (Date, &DailyKey)> {
+        self.days.iter().map(|day| (self.date(day), &day.key))
+    }
+
+    pub fn date(&self, day: &KeySheetDay) -> Date {
+        Date { year: self.year, month: self.month, day: day.day }
+    }
+
+    /// The row for `date`, if the sheet covers it
+    pub fn day(&self, date: Date) -> Option<&KeySheetDay> {
+        if (date.year, date.month) != (self.year, self.month) {
+            return None;
+        }
+        self.days.iter().find(|day| day.day == date.day)
+    }
+
+    /// The row listing `kenngruppe`, as found in the first group of a message
+    pub fn day_by_kenngruppe(&self, kenngruppe: Kenngruppe) -> Option<&KeySheetDay> {
+        self.days.iter().find(|day| day.kenngruppen.contains(&kenngruppe))
+    }
+
+    /// Machine of the sheet's model set up with the key of `day`
+    pub fn enigma(&self, day: &KeySheetDay) -> Result<Enigma, KeyError> {
+        Enigma::from_key(self.model, &day.key)
+    }
+
+    fn has_grundstellung(&self) -> bool {
+        self.days.iter().any(|day| day.key.grundstellung.is_some())
+    }
+}
+
+impl fmt::Display for KeySheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the fields are public, so the month may be out of range
+        let month = match usize::from(self.month).checked_sub(1).and_then(|i| MONTHS.get(i)) {
+            Some(name) => name.to_string(),
+            None => format!("Monat {}", self.month),
+        };
+        writeln!(f, "Geheime Kommandosache!")?;
+        writeln!(
+            f,
+            "{} / {month} {} / Umkehrwalze {}",
+            Self::title(self.model),
+            self.year,
+            self.reflector
+        )?;
+        writeln!(f)?;
+
+        let rows = self.days.iter().rev();
+        let mut columns: Vec<(&str, Vec<String>)> = vec![
+            ("Datum", rows.clone().map(|day| format!("{:>5}", day.day)).collect()),
+            (
+                "Walzenlage",
+                rows.clone()
+                    .map(|day| {
+                        let [left, middle, right] = day.key.walzenlage.rotors;
+                        format!("{left:<4} {middle:<4} {right:<4}")
+                    })
+                    .collect(),
+            ),
+            ("Ringstellung", rows.clone().map(|day| day.key.ringstellung.to_string()).collect()),
+            (
+                "Steckerverbindungen",
+                rows.clone().map(|day| day.key.steckerverbindungen.to_string()).collect(),
+            ),
+        ];
+        if self.has_grundstellung() {
+            columns.push((
+                "Grundstellung",
+                rows.clone()
+                    .map(|day| day.key.grundstellung.map(|g| g.to_string()).unwrap_or_default())
+                    .collect(),
+            ));
+        }
+        columns.push((
+            "Kenngruppen",
+            rows.map(|day| day.kenngruppen.map(|k| k.to_string().to_lowercase()).join(" "))
+                .collect(),
+        ));
+
+        let widths: Vec<usize> = columns
+            .iter()
+            .map(|(title, cells)| cells.iter().map(String::len).chain([title.len()]).max().unwrap())
+            .collect();
+        let line = |cells: Vec<&str>| {
+            let cells: Vec<String> =
+                cells.iter().zip(&widths).map(|(cell, &width)| format!("{cell:<width$}")).collect();
+            cells.join(" | ").trim_end().to_string()
+        };
+
+        writeln!(f, "{}", line(columns.iter().map(|(title, _)| *title).collect()))?;
+        let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+        writeln!(f, "{}", rule.join("-+-"))?;
+        for row in 0..self.days.len() {
+            writeln!(
+                f,
+                "{}",
+                line(columns.iter().map(|(_, cells)| cells[row].as_str()).collect())
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+//! Key sheet import.
+//!
+//! Reads the layout written by the [`Display`](core::fmt::Display)
+//! implementation of [`KeySheet`]:
+//!
+//! 1. Any number of lines without a `/`, e.g. `Geheime Kommandosache!`.
+//! 2. The title `<Schlüssel> / <Monat> <Jahr> / Umkehrwalze <UKW>`, where the
+//!    Schlüssel is `Heeres-Maschinenschlüssel` (Enigma I) or `Marine-Schlüssel
+//!    M` (M3) and the month is spelled in German.
+//! 3. The header: `|` separated column titles in any order. `Datum`,
+//!    `Walzenlage`, `Ringstellung`, `Steckerverbindungen` and `Kenngruppen` are
+//!    required, `Grundstellung` is optional.
+//! 4. Optionally a rule made of `-` and `+`.
+//! 5. One line per day, in any order, with a cell for every column. The
+//!    Walzenlage lists three rotors left to right, ring settings are `01`-`26`
+//!    or `A`-`Z` and the four Kenngruppen may be written in either case.
+//!
+//! Blank lines are ignored everywhere.
+
+use core::fmt;
+use core::str::FromStr;
+
+use super::days_in_month;
+use super::KeySheet;
+use super::KeySheetDay;
+use super::MONTHS;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::historical_machines::model::Model;
+use crate::historical_machines::model::ReflectorId;
+use crate::historical_machines::model::RotorId;
+use crate::key::parse_ring;
+use crate::key::DailyKey;
+use crate::key::Kenngruppe;
+use crate::key::KeyError;
+use crate::key::Ringstellung;
+use crate::key::Steckerverbindungen;
+use crate::key::Walzenlage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseKeySheetErrorKind {
+    MissingTitle,
+    InvalidTitle,
+    UnknownModel,
+    UnknownMonth,
+    InvalidYear,
+    UnknownReflector,
+    MissingHeader,
+    UnknownColumn,
+    DuplicateColumn,
+    MissingColumn(&'static str),
+    CellCount { expected: usize, found: usize },
+    InvalidDay,
+    DuplicateDay(u8),
+    Key(KeyError),
+}
+
+impl fmt::Display for ParseKeySheetErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ParseKeySheetErrorKind::*;
+
+        match self {
+            MissingTitle => {
+                write!(f, "missing title `<Schlüssel> / <Monat> <Jahr> / Umkehrwalze <UKW>`")
+            }
+            InvalidTitle => write!(f, "title must have three parts separated by ` / `"),
+            UnknownModel => {
+                write!(f, "expected `Heeres-Maschinenschlüssel` or `Marine-Schlüssel M`")
+            }
+            UnknownMonth => write!(f, "expected a German month name"),
+            InvalidYear => write!(f, "invalid year"),
+            UnknownReflector => write!(f, "expected `Umkehrwalze A`, `B` or `C`"),
+            MissingHeader => write!(f, "missing column header"),
+            UnknownColumn => write!(f, "unknown column"),
+            DuplicateColumn => write!(f, "column appears twice"),
+            MissingColumn(column) => write!(f, "missing column `{column}`"),
+            CellCount { expected, found } => write!(f, "expected {expected} cells, found {found}"),
+            InvalidDay => write!(f, "day is not in the month"),
+            DuplicateDay(day) => write!(f, "day {day} appears twice"),
+            Key(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Error with the 1-based line and column where it was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseKeySheetError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseKeySheetErrorKind,
+}
+
+impl fmt::Display for ParseKeySheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for ParseKeySheetError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Datum,
+    Walzenlage,
+    Ringstellung,
+    Steckerverbindungen,
+    Grundstellung,
+    Kenngruppen,
+}
+
+impl Column {
+    const ALL: [(Column, &'static str); 6] = [
+        (Column::Datum, "Datum"),
+        (Column::Walzenlage, "Walzenlage"),
+        (Column::Ringstellung, "Ringstellung"),
+        (Column::Steckerverbindungen, "Steckerverbindungen"),
+        (Column::Grundstellung, "Grundstellung"),
+        (Column::Kenngruppen, "Kenngruppen"),
+    ];
+}
+
+/// A piece of a line together with its 1-based column
+#[derive(Debug, Clone, Copy)]
+struct Span<'a> {
+    line: usize,
+    column: usize,
+    text: &'a str,
+}
+
+impl<'a> Span<'a> {
+    fn error(&self, kind: ParseKeySheetErrorKind) -> ParseKeySheetError {
+        ParseKeySheetError { line: self.line, column: self.column, kind }
+    }
+
+    fn key_error(&self, e: KeyError) -> ParseKeySheetError {
+        self.error(ParseKeySheetErrorKind::Key(e))
+    }
+
+    fn sub(&self, start: usize, end: usize) -> Span<'a> {
+        let column = self.column + self.text[..start].chars().count();
+        Span { line: self.line, column, text: &self.text[start..end] }
+    }
+
+    /// Split at `separator`, keeping the separators out of the pieces
+    fn split(&self, separator: &str) -> Vec<Span<'a>> {
+        let mut start = 0;
+        let mut pieces = Vec::new();
+        for (at, _) in self.text.match_indices(separator) {
+            pieces.push(self.sub(start, at));
+            start = at + separator.len();
+        }
+        pieces.push(self.sub(start, self.text.len()));
+        pieces
+    }
+
+    /// Whitespace separated words
+    fn words(&self) -> Vec<Span<'a>> {
+        let mut words = Vec::new();
+        let mut start = None;
+        for (at, ch) in self.text.char_indices().chain([(self.text.len(), ' ')]) {
+            match (start, ch.is_whitespace()) {
+                (None, false) => start = Some(at),
+                (Some(from), true) => {
+                    words.push(self.sub(from, at));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        words
+    }
+
+    /// Exactly `N` words
+    fn words_exact<const N: usize>(&self) -> Result<[Span<'a>; N], ParseKeySheetError> {
+        let words = self.words();
+        match words.get(N) {
+            Some(extra) => Err(extra.key_error(KeyError::UnexpectedField)),
+            None => words.try_into().map_err(|_| self.key_error(KeyError::MissingField)),
+        }
+    }
+}
+
+fn parse_title(title: Span) -> Result<(Model, u16, u8, ReflectorId), ParseKeySheetError> {
+    use ParseKeySheetErrorKind::*;
+
+    let [schluessel, date, reflector] =
+        <[Span; 3]>::try_from(title.split(" / ")).map_err(|_| title.error(InvalidTitle))?;
+    let model = match schluessel.text.trim() {
+        "Heeres-Maschinenschlüssel" => Model::EnigmaI,
+        "Marine-Schlüssel M" => Model::M3,
+        _ => return Err(schluessel.error(UnknownModel)),
+    };
+
+    let [month, year] =
+        date.words_exact().map_err(|e| ParseKeySheetError { kind: UnknownMonth, ..e })?;
+    let month =
+        MONTHS.iter().position(|&name| name == month.text).ok_or(month.error(UnknownMonth))?;
+    let year = year.text.parse().map_err(|_| year.error(InvalidYear))?;
+
+    let reflector = match reflector.words()[..] {
+        [word, id] if word.text == "Umkehrwalze" => {
+            id.text.parse().map_err(|_| id.error(UnknownReflector))?
+        }
+        _ => return Err(reflector.error(UnknownReflector)),
+    };
+    if !model.reflectors().contains(&reflector) {
+        return Err(reflector_error(title, KeyError::ReflectorNotInModel(reflector)));
+    }
+
+    Ok((model, year, month as u8 + 1, reflector))
+}
+
+fn reflector_error(title: Span, e: KeyError) -> ParseKeySheetError {
+    let column = title.text.rfind(' ').map_or(title.column, |at| title.sub(at + 1, at + 1).column);
+    ParseKeySheetError { line: title.line, column, kind: ParseKeySheetErrorKind::Key(e) }
+}
+
+fn parse_header(header: Span) -> Result<Vec<Column>, ParseKeySheetError> {
+    use ParseKeySheetErrorKind::*;
+
+    let mut columns = Vec::new();
+    for cell in header.split("|") {
+        let title = cell.text.trim();
+        let (column, _) = Column::ALL
+            .into_iter()
+            .find(|(_, name)| *name == title)
+            .ok_or(cell.error(UnknownColumn))?;
+        if columns.contains(&column) {
+            return Err(cell.error(DuplicateColumn));
+        }
+        columns.push(column);
+    }
+    for (column, name) in Column::ALL {
+        if column != Column::Grundstellung && !columns.contains(&column) {
+            return Err(header.error(MissingColumn(name)));
+        }
+    }
+    Ok(columns)
+}
+
+struct Sheet {
+    model: Model,
+    reflector: ReflectorId,
+    year: u16,
+    month: u8,
+}
+
+fn parse_day(
+    sheet: &Sheet,
+    columns: &[Column],
+    row: Span,
+) -> Result<KeySheetDay, ParseKeySheetError> {
+    let cells = row.split("|");
+    if cells.len() != columns.len() {
+        let kind =
+            ParseKeySheetErrorKind::CellCount { expected: columns.len(), found: cells.len() };
+        return Err(row.error(kind));
+    }
+
+    let mut day = 0;
+    let mut walzenlage = Walzenlage { reflector: sheet.reflector, rotors: [RotorId::I; 3] };
+    let mut ringstellung = Ringstellung::default();
+    let mut steckerverbindungen = Steckerverbindungen::default();
+    let mut grundstellung = None;
+    let mut kenngruppen = [Kenngruppe([EnigmaAlphabet::new(0); 3]); 4];
+
+    for (column, cell) in columns.iter().zip(cells) {
+        match column {
+            Column::Datum => {
+                let [word] = cell.words_exact()?;
+                day = word
+                    .text
+                    .parse()
+                    .ok()
+                    .filter(|day| (1..=days_in_month(sheet.year, sheet.month)).contains(day))
+                    .ok_or(word.error(ParseKeySheetErrorKind::InvalidDay))?;
+            }
+            Column::Walzenlage => {
+                let words: [Span; 3] = cell.words_exact()?;
+                for (rotor, word) in walzenlage.rotors.iter_mut().zip(&words) {
+                    *rotor =
+                        word.text.parse().map_err(|_| word.key_error(KeyError::UnknownRotor))?;
+                }
+                walzenlage.check(sheet.model).map_err(|e| match e {
+                    KeyError::RotorNotInModel(rotor) | KeyError::RepeatedRotor(rotor) => {
+                        let at = walzenlage.rotors.iter().rposition(|&r| r == rotor).unwrap();
+                        words[at].key_error(e)
+                    }
+                    _ => cell.key_error(e),
+                })?;
+            }
+            Column::Ringstellung => {
+                let words: [Span; 3] = cell.words_exact()?;
+                for (ring, word) in ringstellung.0.iter_mut().zip(&words) {
+                    *ring = parse_ring(word.text).map_err(|e| word.key_error(e))?;
+                }
+            }
+            Column::Steckerverbindungen => {
+                let words = cell.words();
+                for (i, word) in words.iter().enumerate() {
+                    let pairs: Vec<&str> = words[..=i].iter().map(|word| word.text).collect();
+                    steckerverbindungen = pairs.join(" ").parse().map_err(|e| match e {
+                        KeyError::UnexpectedField => word.key_error(KeyError::InvalidLetters),
+                        e => word.key_error(e),
+                    })?;
+                }
+            }
+            Column::Grundstellung => {
+                grundstellung = match cell.words()[..] {
+                    [] => None,
+                    [word] => Some(word.text.parse().map_err(|e| word.key_error(e))?),
+                    [_, extra, ..] => return Err(extra.key_error(KeyError::UnexpectedField)),
+                };
+            }
+            Column::Kenngruppen => {
+                let words: [Span; 4] = cell.words_exact()?;
+                for (kenngruppe, word) in kenngruppen.iter_mut().zip(&words) {
+                    let text = word.text.to_ascii_uppercase();
+                    *kenngruppe = Kenngruppe::from_str(&text).map_err(|e| word.key_error(e))?;
+                }
+            }
+        }
+    }
+
+    let key = DailyKey { walzenlage, ringstellung, steckerverbindungen, grundstellung };
+    Ok(KeySheetDay { day, key, kenngruppen })
+}
+
+fn is_rule(line: &str) -> bool {
+    line.chars().all(|ch| matches!(ch, '-' | '+' | ' '))
+}
+
+impl FromStr for KeySheet {
+    type Err = ParseKeySheetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(i, text)| Span { line: i + 1, column: 1, text })
+            .filter(|line| !line.text.trim().is_empty());
+        let end = Span { line: s.lines().count() + 1, column: 1, text: "" };
+
+        let title = lines
+            .find(|line| line.text.contains('/'))
+            .ok_or(end.error(ParseKeySheetErrorKind::MissingTitle))?;
+        let (model, year, month, reflector) = parse_title(title)?;
+        let sheet = Sheet { model, reflector, year, month };
+
+        let header = lines.next().ok_or(end.error(ParseKeySheetErrorKind::MissingHeader))?;
+        let columns = parse_header(header)?;
+
+        let mut days: Vec<KeySheetDay> = Vec::new();
+        for row in lines.filter(|line| !is_rule(line.text)) {
+            let day = parse_day(&sheet, &columns, row)?;
+            if days.iter().any(|other| other.day == day.day) {
+                return Err(row.error(ParseKeySheetErrorKind::DuplicateDay(day.day)));
+            }
+            days.push(day);
+        }
+        days.sort_by_key(|day| day.day);
+
+        Ok(KeySheet { model, reflector, year, month, days })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::generator::generate;
+    use super::super::generator::KeySheetOptions;
+    use super::super::Date;
+    use super::*;
+
+    const SHEET: &str = "\
+Geheime Kommandosache!
+Heeres-Maschinenschlüssel / Juni 1941 / Umkehrwalze B
+
+Datum | Walzenlage     | Ringstellung | Steckerverbindungen           | Kenngruppen
+------+----------------+--------------+-------------------------------+----------------
+   22 | II   IV   V    | 02 21 12     | AV BS CG DL FU HZ IN KM OW RX | adx ayn nuq wkl
+   21 | I    V    III  | 14 09 24     | SZ GT DV KU FO MY EW JN IX LQ | dgy xei jkm ldv
+";
+
+    fn error(sheet: &str) -> (usize, usize, ParseKeySheetErrorKind) {
+        let e = sheet.parse::<KeySheet>().unwrap_err();
+        (e.line, e.column, e.kind)
+    }
+
+    #[test]
+    fn test_parse_sheet() {
+        let sheet: KeySheet = SHEET.parse().unwrap();
+        assert_eq!(
+            (sheet.model, sheet.reflector, sheet.year, sheet.month),
+            (Model::EnigmaI, ReflectorId::B, 1941, 6)
+        );
+        assert_eq!(sheet.days.len(), 2);
+        assert_eq!(sheet.days[1].day, 22);
+        assert_eq!(
+            sheet.days[1].key,
+            "B II IV V 02 21 12 AV BS CG DL FU HZ IN KM OW RX".parse().unwrap()
+        );
+        assert_eq!(sheet.days[0].kenngruppen[3], "LDV".parse().unwrap());
+
+        for month in [0, 13] {
+            let sheet = KeySheet { month, ..sheet.clone() };
+            assert!(sheet.to_string().contains(&format!("/ Monat {month} 1941 /")));
+        }
+
+        for options in [KeySheetOptions::heer(), KeySheetOptions::kriegsmarine()] {
+            let sheet = generate(&options, 1944, 2, 3).unwrap();
+            assert_eq!(sheet.to_string().parse::<KeySheet>().unwrap(), sheet);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        use ParseKeySheetErrorKind::*;
+
+        assert_eq!(
+            error(&SHEET.replace(" V    III", " V    V  ")),
+            (7, 19, Key(KeyError::RepeatedRotor(RotorId::V)))
+        );
+        assert_eq!(
+            error(&SHEET.replace(" II   IV", " II   IX")),
+            (6, 14, Key(KeyError::UnknownRotor))
+        );
+        assert_eq!(
+            error(&SHEET.replace("14 09 24", "14 39 24")),
+            (7, 29, Key(KeyError::InvalidRing))
+        );
+        assert_eq!(
+            error(&SHEET.replace("FO MY", "FO MS")),
+            (7, 56, Key(KeyError::RepeatedLetter('S'.into())))
+        );
+        assert_eq!(error(&SHEET.replace("   22 |", "   31 |")), (6, 4, InvalidDay));
+        assert_eq!(error(&SHEET.replace("   22 |", "   21 |")), (7, 1, DuplicateDay(21)));
+        assert_eq!(
+            error(&SHEET.replace(" ldv", " ldv | x")),
+            (7, 1, CellCount { expected: 5, found: 6 })
+        );
+        assert_eq!(error(&SHEET.replace("Juni", "Juno")), (2, 29, UnknownMonth));
+        assert_eq!(
+            error(&SHEET.replace("| Kenngruppen", "")),
+            (4, 1, MissingColumn("Kenngruppen"))
+        );
+        assert_eq!(error("Geheime Kommandosache!\n"), (2, 1, MissingTitle));
+        assert_eq!(
+            SHEET.replace(" II   IV", " II   IX").parse::<KeySheet>().unwrap_err().to_string(),
+            "line 6, column 14: unknown rotor"
+        );
+    }
+
+    #[test]
+    fn test_select_daily_key() {
+        let sheet: KeySheet = SHEET.parse().unwrap();
+
+        let day = sheet.day(Date::new(1941, 6, 22).unwrap()).unwrap();
+        assert_eq!(day.day, 22);
+        assert!(sheet.day(Date::new(1941, 6, 23).unwrap()).is_none());
+        assert!(sheet.day(Date::new(1941, 7, 22).unwrap()).is_none());
+
+        let by_kenngruppe = sheet.day_by_kenngruppe("NUQ".parse().unwrap()).unwrap();
+        assert_eq!(by_kenngruppe, day);
+        assert_eq!(
+            sheet.keys().map(|(date, _)| date.to_string()).collect::<Vec<_>>(),
+            ["1941-06-21", "1941-06-22"]
+        );
+
+        let mut enigma = sheet.enigma(day).unwrap();
+        enigma.set_positions(['W', 'X', 'C']);
+        let message_key: String = "KCH".chars().map(|ch| enigma.encode(ch).to_char()).collect();
+        assert_eq!(message_key, "BLA");
+    }
+}
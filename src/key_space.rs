@@ -0,0 +1,475 @@
+//! Size of the key space
+//!
+//! Counts the keys of a machine exactly, factor by factor: the reflector, the
+//! wheel order, the ring settings, the start position and the plugboard. The
+//! numbers outgrow every machine integer, so they are kept in a [`BigUint`].
+//!
+//! Not every key enciphers differently. The ring of the left rotor only moves
+//! the letters shown in its window: the rotor never carries another, so a
+//! different ring with the start position moved to match gives the same
+//! machine. The effective count leaves those out.
+
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use crate::historical_machines::model::Model;
+use crate::historical_machines::model::ReflectorId;
+use crate::historical_machines::model::RotorId;
+use crate::key::KeyError;
+
+/// Unsigned integer of any size
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigUint {
+    /// Base 2^32 digits, least significant first, without leading zeros
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn one() -> Self {
+        Self::from(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    fn normalize(mut self) -> Self {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        self
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let mut digits = Vec::with_capacity(self.digits.len().max(other.digits.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..self.digits.len().max(other.digits.len()) {
+            let sum = carry
+                + u64::from(self.digits.get(i).copied().unwrap_or(0))
+                + u64::from(other.digits.get(i).copied().unwrap_or(0));
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        Self { digits }.normalize()
+    }
+
+    /// `self - other`, `None` if `other` is larger
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if *self < *other {
+            return None;
+        }
+        let mut digits = Vec::with_capacity(self.digits.len());
+        let mut borrow = 0i64;
+        for (i, &digit) in self.digits.iter().enumerate() {
+            let mut difference =
+                i64::from(digit) - i64::from(other.digits.get(i).copied().unwrap_or(0)) - borrow;
+            borrow = i64::from(difference < 0);
+            difference += borrow << 32;
+            digits.push(difference as u32);
+        }
+        Some(Self { digits }.normalize())
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.digits.iter().enumerate() {
+                let product = u64::from(a) * u64::from(b) + u64::from(digits[i + j]) + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        Self { digits }.normalize()
+    }
+
+    /// Quotient and remainder of the division by `divisor`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `divisor` is zero.
+    pub fn div_rem(&self, divisor: u32) -> (Self, u32) {
+        assert!(divisor != 0, "division by zero");
+        let mut digits = vec![0u32; self.digits.len()];
+        let mut remainder = 0u64;
+        for (i, &digit) in self.digits.iter().enumerate().rev() {
+            let current = remainder << 32 | u64::from(digit);
+            digits[i] = (current / u64::from(divisor)) as u32;
+            remainder = current % u64::from(divisor);
+        }
+        (Self { digits }.normalize(), remainder as u32)
+    }
+
+    /// Base 2 logarithm, 0 for zero
+    pub fn bits(&self) -> f64 {
+        let top = self.digits.len().saturating_sub(2);
+        let mantissa = self.digits[top..]
+            .iter()
+            .rev()
+            .fold(0.0, |value, &digit| value * 4_294_967_296.0 + f64::from(digit));
+        match self.is_zero() {
+            true => 0.0,
+            false => mantissa.log2() + 32.0 * top as f64,
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        Self { digits: vec![value as u32, (value >> 32) as u32] }.normalize()
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let by_length = self.digits.len().cmp(&other.digits.len());
+        by_length.then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    /// Decimal
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut chunks = Vec::new();
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (quotient, chunk) = rest.div_rem(1_000_000_000);
+            chunks.push(chunk);
+            rest = quotient;
+        }
+        let mut decimal = match chunks.pop() {
+            Some(first) => first.to_string(),
+            None => "0".into(),
+        };
+        for chunk in chunks.iter().rev() {
+            decimal.push_str(&format!("{chunk:09}"));
+        }
+        f.pad_integral(true, "", &decimal)
+    }
+}
+
+/// Procedure rules that forbid keys the machine could be set to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rules {
+    /// No rotor stands where it stood the day before
+    pub no_repeated_slots: bool,
+    /// No plug joins neighbours in the alphabet, like `AB` or `ST`
+    pub no_adjacent_plugs: bool,
+}
+
+/// One part of the key and its number of settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Factor {
+    pub name: &'static str,
+    pub count: BigUint,
+    /// Settings that encipher differently
+    pub effective: BigUint,
+}
+
+impl Factor {
+    fn new(name: &'static str, count: BigUint) -> Self {
+        Self { name, effective: count.clone(), count }
+    }
+}
+
+/// Machine and procedure whose keys are counted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySpace {
+    rotors: usize,
+    reflectors: usize,
+    cables: RangeInclusive<usize>,
+    rings: bool,
+    rules: Rules,
+}
+
+impl KeySpace {
+    /// Every rotor and reflector of `model`, ten cables and the rings counted
+    pub fn new(model: Model) -> Self {
+        Self {
+            rotors: model.rotors().len(),
+            reflectors: model.reflectors().len(),
+            cables: 10..=10,
+            rings: true,
+            rules: Rules::default(),
+        }
+    }
+
+    /// The rotors issued, three of which go into the machine. A rotor listed
+    /// twice counts once.
+    pub fn with_rotors(mut self, rotors: &[RotorId]) -> Self {
+        self.rotors = distinct(rotors);
+        self
+    }
+
+    pub fn with_reflectors(mut self, reflectors: &[ReflectorId]) -> Self {
+        self.reflectors = distinct(reflectors);
+        self
+    }
+
+    /// Numbers of plug cables a key may use, e.g. `5..=8` in 1938. An empty
+    /// range makes [`factors`](Self::factors) fail.
+    pub fn with_cables(mut self, cables: RangeInclusive<usize>) -> Self {
+        self.cables = cables;
+        self
+    }
+
+    /// Whether the ring settings are part of the key
+    pub fn with_rings(mut self, rings: bool) -> Self {
+        self.rings = rings;
+        self
+    }
+
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// The count of every part of the key
+    pub fn factors(&self) -> Result<Vec<Factor>, KeyError> {
+        if self.cables.is_empty() {
+            return Err(KeyError::NoCables);
+        }
+        if *self.cables.end() > 13 {
+            return Err(KeyError::TooManyCables(*self.cables.end()));
+        }
+
+        let n = self.rotors as u64;
+        let arrangements = |n: u64, k: u64| (0..k).map(|i| n.saturating_sub(i)).product::<u64>();
+        let wheel_orders = match self.rules.no_repeated_slots {
+            // inclusion-exclusion over the rotors back in yesterday's slot
+            true => (0..=3u64)
+                .map(|back| {
+                    binomial(3, back) as i64 * arrangements(n - back.min(n), 3 - back) as i64
+                })
+                .enumerate()
+                .map(|(back, count)| if back % 2 == 0 { count } else { -count })
+                .sum::<i64>()
+                .max(0) as u64,
+            false => arrangements(n, 3),
+        };
+
+        let rings = match self.rings {
+            // the left ring only relabels the start position
+            true => Factor {
+                effective: BigUint::from(26 * 26),
+                ..Factor::new("ring settings", 26u64.pow(3).into())
+            },
+            false => Factor::new("ring settings", BigUint::one()),
+        };
+
+        let mut plugboard = BigUint::zero();
+        for cables in self.cables.clone() {
+            plugboard = plugboard.add(&plug_pairings(26, cables, self.rules.no_adjacent_plugs));
+        }
+
+        Ok(vec![
+            Factor::new("reflector", BigUint::from(self.reflectors as u64)),
+            Factor::new("wheel order", BigUint::from(wheel_orders)),
+            rings,
+            Factor::new("start position", BigUint::from(26u64.pow(3))),
+            Factor::new("plugboard", plugboard),
+        ])
+    }
+
+    /// Number of keys
+    pub fn total(&self) -> Result<BigUint, KeyError> {
+        Ok(self.factors()?.iter().fold(BigUint::one(), |total, factor| total.mul(&factor.count)))
+    }
+
+    /// Number of keys that encipher differently
+    pub fn effective(&self) -> Result<BigUint, KeyError> {
+        let factors = self.factors()?;
+        Ok(factors.iter().fold(BigUint::one(), |total, factor| total.mul(&factor.effective)))
+    }
+}
+
+impl fmt::Display for KeySpace {
+    /// Table of the factors with their counts and effective counts, each with
+    /// its bits, then the totals. Fails where [`KeySpace::factors`] does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let factors = self.factors().map_err(|_| fmt::Error)?;
+        let total = Factor {
+            name: "total",
+            count: factors.iter().fold(BigUint::one(), |total, factor| total.mul(&factor.count)),
+            effective: factors
+                .iter()
+                .fold(BigUint::one(), |total, factor| total.mul(&factor.effective)),
+        };
+        write!(f, "{:<16}{:>28}{:>41}", "", "count", "effective")?;
+        for factor in factors.iter().chain([&total]) {
+            write!(
+                f,
+                "\n{:<16}{:>28}{:>8.2} bits{:>28}{:>8.2} bits",
+                factor.name,
+                factor.count,
+                factor.count.bits(),
+                factor.effective,
+                factor.effective.bits()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of different items in `items`
+fn distinct<T: PartialEq>(items: &[T]) -> usize {
+    items.iter().enumerate().filter(|(i, item)| !items[..*i].contains(item)).count()
+}
+
+fn binomial(n: u64, k: u64) -> u64 {
+    (0..k).fold(1, |value, i| value * (n - i) / (i + 1))
+}
+
+/// Ways to join `letters` letters with `cables` cables, optionally never
+/// joining neighbours in the alphabet
+fn plug_pairings(letters: usize, cables: usize, no_adjacent: bool) -> BigUint {
+    // pairings[n][k]: k pairs among n letters, from joining the last letter
+    // to one of the others or leaving it out
+    let mut pairings = vec![vec![BigUint::zero(); cables + 1]; letters + 1];
+    for row in &mut pairings {
+        row[0] = BigUint::one();
+    }
+    for n in 2..=letters {
+        for k in 1..=cables {
+            let joined = pairings[n - 2][k - 1].mul(&BigUint::from(n as u64 - 1));
+            pairings[n][k] = pairings[n - 1][k].add(&joined);
+        }
+    }
+    if !no_adjacent {
+        return pairings[letters][cables].clone();
+    }
+
+    // inclusion-exclusion over the `used` neighbour pairs that are plugged,
+    // of which a row of `letters` letters holds binomial(letters - used, used)
+    let (mut plus, mut minus) = (BigUint::zero(), BigUint::zero());
+    for used in 0..=cables.min(letters / 2) {
+        let ways = BigUint::from(binomial((letters - used) as u64, used as u64))
+            .mul(&pairings[letters - 2 * used][cables - used]);
+        match used % 2 {
+            0 => plus = plus.add(&ways),
+            _ => minus = minus.add(&ways),
+        }
+    }
+    plus.checked_sub(&minus).expect("inclusion-exclusion does not go negative")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::Walzenlage;
+
+    #[test]
+    fn test_big_uint() {
+        let big = BigUint::from(u64::MAX).mul(&BigUint::from(u64::MAX));
+        assert_eq!(big.to_string(), "340282366920938463426481119284349108225");
+        assert_eq!(big.add(&BigUint::one()).checked_sub(&big), Some(BigUint::one()));
+        assert_eq!(BigUint::one().checked_sub(&big), None);
+        assert_eq!(big.div_rem(10).1, 5);
+        assert!((big.bits() - 128.0).abs() < 1e-9);
+        assert_eq!(BigUint::zero().to_string(), "0");
+        assert_eq!(format!("{:>4}", BigUint::from(42)), "  42");
+    }
+
+    #[test]
+    fn test_enigma_i_with_ten_cables() {
+        // the figure usually quoted, with three rotors of five, UKW B and no
+        // rings
+        let key_space =
+            KeySpace::new(Model::EnigmaI).with_reflectors(&[ReflectorId::B]).with_rings(false);
+        let factors = key_space.factors().unwrap();
+        assert_eq!(factors[1].count, BigUint::from(60));
+        assert_eq!(factors[4].count.to_string(), "150738274937250");
+        let total = key_space.total().unwrap();
+        assert_eq!(total.to_string(), "158962555217826360000");
+        assert!((total.bits() - 67.1).abs() < 0.01);
+        assert_eq!(key_space.effective().unwrap(), total);
+
+        let with_rings = key_space.with_rings(true);
+        let total = with_rings.total().unwrap();
+        assert_eq!(total, BigUint::from(158_962_555_217_826_360).mul(&BigUint::from(17_576_000)));
+        assert_eq!(with_rings.effective().unwrap().mul(&BigUint::from(26)), total);
+        let table = with_rings.to_string();
+        assert_eq!(table.lines().count(), 7);
+        let ring_settings: Vec<_> = table.lines().nth(3).unwrap().split_whitespace().collect();
+        assert_eq!(
+            ring_settings,
+            ["ring", "settings", "17576", "14.10", "bits", "676", "9.40", "bits"]
+        );
+    }
+
+    #[test]
+    fn test_cables() {
+        let plugs = |cables| {
+            let key_space = KeySpace::new(Model::M3).with_cables(cables);
+            key_space.factors().map(|factors| factors[4].count.to_string())
+        };
+        assert_eq!(plugs(0..=0), Ok("1".into()));
+        assert_eq!(plugs(11..=11), Ok("205552193096250".into()));
+        assert_eq!(plugs(13..=13), Ok("7905853580625".into()));
+        assert_eq!(plugs(0..=13), Ok("532985208200576".into()));
+        assert_eq!(plugs(0..=14), Err(KeyError::TooManyCables(14)));
+        assert_eq!(plugs(RangeInclusive::new(8, 5)), Err(KeyError::NoCables));
+
+        let key_space = KeySpace::new(Model::M3).with_cables(0..=14);
+        assert!(fmt::write(&mut String::new(), format_args!("{key_space}")).is_err());
+    }
+
+    #[test]
+    fn test_parts_listed_twice() {
+        let key_space = KeySpace::new(Model::EnigmaI)
+            .with_rotors(&[RotorId::I, RotorId::II, RotorId::III])
+            .with_reflectors(&[ReflectorId::B]);
+        let twice = KeySpace::new(Model::EnigmaI)
+            .with_rotors(&[RotorId::I, RotorId::II, RotorId::III, RotorId::I])
+            .with_reflectors(&[ReflectorId::B, ReflectorId::B]);
+        assert_eq!(twice, key_space);
+        assert_eq!(twice.factors().unwrap()[1].count, BigUint::from(6));
+    }
+
+    #[test]
+    fn test_rules() {
+        // yesterday's wheel order B I II III rules out any rotor in its slot
+        let yesterday = [RotorId::I, RotorId::II, RotorId::III];
+        let allowed = Walzenlage::all(Model::EnigmaI)
+            .filter(|order| order.reflector == ReflectorId::B)
+            .filter(|order| order.rotors.iter().zip(yesterday).all(|(&rotor, old)| rotor != old))
+            .count();
+        let rules = Rules { no_repeated_slots: true, ..Rules::default() };
+        let key_space = KeySpace::new(Model::EnigmaI).with_rules(rules);
+        assert_eq!(key_space.factors().unwrap()[1].count, BigUint::from(allowed as u64));
+        assert_eq!(allowed, 32);
+
+        // pairings of eight letters without neighbours, counted one by one
+        for cables in 0..=4 {
+            let mut count = 0;
+            let mut stack = vec![(0u32, 0)];
+            while let Some((used, pairs)) = stack.pop() {
+                let Some(first) = (0..8).find(|i| used & 1 << i == 0) else {
+                    count += usize::from(pairs == cables);
+                    continue;
+                };
+                stack.push((used | 1 << first, pairs));
+                for second in first + 2..8 {
+                    if used & 1 << second == 0 {
+                        stack.push((used | 1 << first | 1 << second, pairs + 1));
+                    }
+                }
+            }
+            assert_eq!(plug_pairings(8, cables, true), BigUint::from(count as u64), "{cables}");
+        }
+        assert!(plug_pairings(26, 10, true) < plug_pairings(26, 10, false));
+    }
+}
@@ -1,2 +1,18 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod conventions;
 pub mod core;
 pub mod historical_machines;
+pub mod key;
+#[cfg(feature = "std")]
+pub mod key_sheet;
+#[cfg(feature = "std")]
+pub mod key_space;
+#[cfg(feature = "std")]
+pub mod message;
+#[cfg(feature = "std")]
+pub mod procedure;
+pub mod random;
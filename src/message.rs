@@ -0,0 +1,375 @@
+//! # Radio messages
+//!
+//! Layout of a signal as it went over the air: a header followed by the body
+//! in groups of five letters (four for the Kriegsmarine).
+//!
+//! ```text
+//! 1840 - 2TLE - 1TL - 174 - WXC KCH -
+//! EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE ...
+//! ```
+//!
+//! The header holds the time of origin, for messages in several parts the
+//! number of parts (`2TLE`, zwei Teile) and the part number (`1TL`, erster
+//! Teil), the number of letters in the body and the indicator. Messages longer
+//! than [`MAX_PART_LETTERS`] were split into parts, each sent with its own
+//! message key.
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::historical_machines::model::Model;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::key::MessageKey;
+use crate::procedure::clear_start;
+use crate::procedure::ProcedureError;
+use crate::procedure::Transmission;
+
+/// Longest body a single part was allowed to have
+pub const MAX_PART_LETTERS: usize = 250;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageError {
+    InvalidHeader,
+    InvalidLetter(char),
+    /// The header announces a different number of letters than the body has
+    LetterCountMismatch {
+        declared: usize,
+        actual: usize,
+    },
+    /// A part is missing, repeated or numbered beyond the announced count
+    InvalidParts,
+    /// Plaintext without a letter to send
+    EmptyPlaintext,
+    Procedure(ProcedureError),
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::InvalidHeader => write!(f, "invalid message header"),
+            MessageError::InvalidLetter(ch) => write!(f, "{ch:?} is not a letter A-Z"),
+            MessageError::LetterCountMismatch { declared, actual } => {
+                write!(f, "header announces {declared} letters but the body has {actual}")
+            }
+            MessageError::InvalidParts => write!(f, "parts are missing or repeated"),
+            MessageError::EmptyPlaintext => write!(f, "plaintext has no letters"),
+            MessageError::Procedure(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+impl From<ProcedureError> for MessageError {
+    fn from(value: ProcedureError) -> Self {
+        MessageError::Procedure(value)
+    }
+}
+
+/// Write `letters` in groups of `size`, e.g. `EDPUD NRGYS ZR`
+pub fn group(letters: &str, size: usize) -> String {
+    let groups: Vec<&str> = letters
+        .as_bytes()
+        .chunks(size)
+        .map(|group| core::str::from_utf8(group).expect("letters are ASCII"))
+        .collect();
+    groups.join(" ")
+}
+
+/// Join grouped text back into a run of letters, rejecting anything but
+/// whitespace and `A`-`Z`
+pub fn ungroup(text: &str) -> Result<String, MessageError> {
+    text.chars()
+        .filter(|ch| !ch.is_whitespace())
+        .map(
+            |ch| {
+                if ch.is_ascii_uppercase() {
+                    Ok(ch)
+                } else {
+                    Err(MessageError::InvalidLetter(ch))
+                }
+            },
+        )
+        .collect()
+}
+
+/// Time of origin, written `HHMM`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}{:02}", self.hour, self.minute)
+    }
+}
+
+impl FromStr for Time {
+    type Err = MessageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 4 || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(MessageError::InvalidHeader);
+        }
+        let hour = s[..2].parse().map_err(|_| MessageError::InvalidHeader)?;
+        let minute = s[2..].parse().map_err(|_| MessageError::InvalidHeader)?;
+        if hour > 23 || minute > 59 {
+            return Err(MessageError::InvalidHeader);
+        }
+        Ok(Self { hour, minute })
+    }
+}
+
+/// Preamble of a message, `1840 - 2TLE - 1TL - 174 - WXC KCH -`. Single-part
+/// messages leave out the part fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub time: Time,
+    pub parts: u8,
+    /// 1-based number of this part
+    pub part: u8,
+    pub letters: usize,
+    pub indicator: String,
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - ", self.time)?;
+        if self.parts > 1 {
+            write!(f, "{}TLE - {}TL - ", self.parts, self.part)?;
+        }
+        write!(f, "{} - {} -", self.letters, self.indicator)
+    }
+}
+
+impl FromStr for Header {
+    type Err = MessageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split('-').map(str::trim).collect();
+        let (time, parts, part, letters, indicator) = match fields[..] {
+            [time, letters, indicator, ""] => (time, "1TLE", "1TL", letters, indicator),
+            [time, parts, part, letters, indicator, ""] => (time, parts, part, letters, indicator),
+            _ => return Err(MessageError::InvalidHeader),
+        };
+        let number = |field: &str, suffix: &str| -> Result<u8, MessageError> {
+            let number = field.strip_suffix(suffix).and_then(|n| n.trim().parse().ok());
+            number.filter(|&n| n > 0).ok_or(MessageError::InvalidHeader)
+        };
+
+        let header = Self {
+            time: time.parse()?,
+            parts: number(parts, "TLE")?,
+            part: number(part, "TL")?,
+            letters: letters.parse().map_err(|_| MessageError::InvalidHeader)?,
+            indicator: indicator.split_whitespace().collect::<Vec<_>>().join(" "),
+        };
+        if header.part > header.parts || header.indicator.is_empty() {
+            return Err(MessageError::InvalidHeader);
+        }
+        Ok(header)
+    }
+}
+
+/// One part of a signal: header and ungrouped body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub header: Header,
+    pub body: String,
+}
+
+impl Message {
+    /// Body in groups of `size` letters below the header
+    pub fn to_grouped(&self, size: usize) -> String {
+        format!("{}\n{}", self.header, group(&self.body, size))
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_grouped(5))
+    }
+}
+
+impl FromStr for Message {
+    type Err = MessageError;
+
+    /// Parse a header line followed by the grouped body, checking the letter
+    /// count announced in the header
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (header, body) = s.split_once('\n').unwrap_or((s, ""));
+        let header: Header = header.parse()?;
+        let body = ungroup(body)?;
+        if body.len() != header.letters {
+            return Err(MessageError::LetterCountMismatch {
+                declared: header.letters,
+                actual: body.len(),
+            });
+        }
+        Ok(Self { header, body })
+    }
+}
+
+/// Cut `plaintext` into parts of at most `max_letters`. Fails without a letter
+/// to cut, as there would be no part to send.
+pub fn split(plaintext: &str, max_letters: usize) -> Result<Vec<String>, MessageError> {
+    let letters = ungroup(plaintext)?;
+    if letters.is_empty() {
+        return Err(MessageError::EmptyPlaintext);
+    }
+    Ok(letters
+        .as_bytes()
+        .chunks(max_letters.max(1))
+        .map(|part| String::from_utf8(part.to_vec()).expect("letters are ASCII"))
+        .collect())
+}
+
+/// Encipher `plaintext` with the clear-start procedure, split into parts of at
+/// most `max_letters`. `choose_keys` is asked for the indicator start and the
+/// message key of every part, numbered from 1.
+pub fn send(
+    model: Model,
+    key: &DailyKey,
+    time: Time,
+    plaintext: &str,
+    max_letters: usize,
+    mut choose_keys: impl FnMut(u8) -> (Grundstellung, MessageKey),
+) -> Result<Vec<Message>, MessageError> {
+    let parts = split(plaintext, max_letters)?;
+    let count = u8::try_from(parts.len()).map_err(|_| MessageError::InvalidParts)?;
+    (1..)
+        .zip(parts)
+        .map(|(part, text)| {
+            let (start, message_key) = choose_keys(part);
+            let Transmission { indicator, body } =
+                clear_start::send(model, key, start, message_key, &text)?;
+            let header = Header { time, parts: count, part, letters: body.len(), indicator };
+            Ok(Message { header, body })
+        })
+        .collect()
+}
+
+/// Decipher the parts of a message, in any order, and join the plaintexts
+pub fn receive(model: Model, key: &DailyKey, parts: &[Message]) -> Result<String, MessageError> {
+    let mut ordered = parts.iter().collect::<Vec<_>>();
+    ordered.sort_by_key(|message| message.header.part);
+    let complete = ordered.iter().enumerate().all(|(i, message)| {
+        usize::from(message.header.part) == i + 1
+            && usize::from(message.header.parts) == parts.len()
+    });
+    if parts.is_empty() || !complete {
+        return Err(MessageError::InvalidParts);
+    }
+
+    let mut plaintext = String::new();
+    for message in ordered {
+        let received = clear_start::receive(model, key, &message.header.indicator, &message.body)?;
+        plaintext.push_str(&received.plaintext);
+    }
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PART_1: &str = "\
+1840 - 2TLE - 1TL - 174 - WXC KCH -
+EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE FGUEY SIOZV EQMIK UBPMM YLKLT TDEIS MDICA
+GYKUA CTCDO MOHWX MUUIA UBSTS LRNBZ SZWNR FXWFY SSXJZ VIJHI DISHP RKLKA YUPAD TXQSP INQMA
+TLPIF SVKDA SCTAC DPBOP VHJK";
+
+    fn key() -> DailyKey {
+        "B II IV V 02 21 12 AV BS CG DL FU HZ IN KM OW RX".parse().unwrap()
+    }
+
+    #[test]
+    fn test_group_and_ungroup() {
+        assert_eq!(group("EDPUDNRGYSZR", 5), "EDPUD NRGYS ZR");
+        assert_eq!(group("EDPUDNRG", 4), "EDPU DNRG");
+        assert_eq!(ungroup("EDPUD NRGYS\nZR").unwrap(), "EDPUDNRGYSZR");
+        assert_eq!(ungroup("EDPUD-"), Err(MessageError::InvalidLetter('-')));
+    }
+
+    #[test]
+    fn test_header() {
+        let header: Header = "1840 - 2TLE - 1TL - 174 - WXC KCH -".parse().unwrap();
+        assert_eq!(header.time, Time { hour: 18, minute: 40 });
+        assert_eq!((header.parts, header.part, header.letters), (2, 1, 174));
+        assert_eq!(header.indicator, "WXC KCH");
+        assert_eq!(header.to_string(), "1840 - 2TLE - 1TL - 174 - WXC KCH -");
+
+        let header: Header = "0915 - 49 - DEB DOX -".parse().unwrap();
+        assert_eq!((header.parts, header.part), (1, 1));
+        assert_eq!(header.to_string(), "0915 - 49 - DEB DOX -");
+
+        for invalid in ["2540 - 49 - DEB DOX -", "0915 - 1TLE - 2TL - 49 - DEB DOX -", "0915 - 49"]
+        {
+            assert_eq!(invalid.parse::<Header>(), Err(MessageError::InvalidHeader));
+        }
+    }
+
+    #[test]
+    fn test_operation_barbarossa_part() {
+        let message: Message = PART_1.parse().unwrap();
+        assert_eq!(message.body.len(), 174);
+        let received =
+            clear_start::receive(Model::EnigmaI, &key(), &message.header.indicator, &message.body)
+                .unwrap();
+        assert!(received.plaintext.starts_with("AUFKLXABTEILUNGXVONXKURTINOWA"));
+
+        assert_eq!(
+            PART_1.replace("174", "180").parse::<Message>(),
+            Err(MessageError::LetterCountMismatch { declared: 180, actual: 174 })
+        );
+    }
+
+    #[test]
+    fn test_multi_part_round_trip() {
+        let plaintext = "AUFKLXABTEILUNGXVONXKURTINOWAX".repeat(20);
+        let keys = ["WXC BLA", "CRS LSD", "QAP ZOT"];
+        let time = Time { hour: 18, minute: 40 };
+        let parts = send(Model::EnigmaI, &key(), time, &plaintext, MAX_PART_LETTERS, |part| {
+            let (start, message_key) = keys[usize::from(part) - 1].split_once(' ').unwrap();
+            (start.parse().unwrap(), message_key.parse().unwrap())
+        })
+        .unwrap();
+
+        assert_eq!(parts.len(), 3);
+        assert_eq!(
+            parts.iter().map(|part| part.header.letters).collect::<Vec<_>>(),
+            [250, 250, 100]
+        );
+        assert_eq!(parts[0].header.to_string(), "1840 - 3TLE - 1TL - 250 - WXC KCH -");
+
+        let sent: Vec<Message> =
+            parts.iter().rev().map(|part| part.to_string().parse().unwrap()).collect();
+        assert_eq!(receive(Model::EnigmaI, &key(), &sent).unwrap(), plaintext);
+        assert_eq!(receive(Model::EnigmaI, &key(), &sent[1..]), Err(MessageError::InvalidParts));
+    }
+
+    #[test]
+    fn test_empty_plaintext() {
+        assert_eq!(split(" \n", MAX_PART_LETTERS), Err(MessageError::EmptyPlaintext));
+        let time = Time { hour: 18, minute: 40 };
+        let sent = send(Model::EnigmaI, &key(), time, "", MAX_PART_LETTERS, |_| {
+            unreachable!("no part to choose keys for")
+        });
+        assert_eq!(sent, Err(MessageError::EmptyPlaintext));
+    }
+
+    #[test]
+    fn test_header_without_body() {
+        let message: Message = "  0915 - 0 - DEB DOX - \t".parse().unwrap();
+        assert_eq!(message.header.indicator, "DEB DOX");
+        assert_eq!(message.body, "");
+        assert_eq!(
+            "\n0915 - 49 - DEB DOX -  ".parse::<Message>(),
+            Err(MessageError::LetterCountMismatch { declared: 49, actual: 0 })
+        );
+    }
+}
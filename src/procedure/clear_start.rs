@@ -0,0 +1,91 @@
+//! Heer and Luftwaffe procedure from May 1940.
+//!
+//! The operator picks a random start position and sends it in the clear. The
+//! message key is enciphered once at that start and sent after it (e.g.
+//! `WXC KCH`), and the body is enciphered at the message key.
+
+use super::type_text;
+use super::ProcedureError;
+use super::Received;
+use super::Transmission;
+use crate::core::engine::Enigma;
+use crate::historical_machines::model::Model;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::key::MessageKey;
+
+pub fn send(
+    model: Model,
+    key: &DailyKey,
+    start: Grundstellung,
+    message_key: MessageKey,
+    plaintext: &str,
+) -> Result<Transmission, ProcedureError> {
+    let mut enigma = Enigma::from_key(model, key)?;
+    enigma.set_positions(start.0);
+    let enciphered_key = type_text(&mut enigma, &message_key.to_string())?;
+
+    enigma.set_message_key(message_key);
+    let body = type_text(&mut enigma, plaintext)?;
+    Ok(Transmission { indicator: format!("{start} {enciphered_key}"), body })
+}
+
+pub fn receive(
+    model: Model,
+    key: &DailyKey,
+    indicator: &str,
+    body: &str,
+) -> Result<Received, ProcedureError> {
+    let mut fields = indicator.split_whitespace();
+    let (Some(start), Some(enciphered_key), None) = (fields.next(), fields.next(), fields.next())
+    else {
+        return Err(ProcedureError::InvalidIndicator);
+    };
+    let start: Grundstellung = start.parse().map_err(|_| ProcedureError::InvalidIndicator)?;
+    if enciphered_key.parse::<MessageKey>().is_err() {
+        return Err(ProcedureError::InvalidIndicator);
+    }
+
+    let mut enigma = Enigma::from_key(model, key)?;
+    enigma.set_positions(start.0);
+    let message_key: MessageKey = type_text(&mut enigma, enciphered_key)?.parse()?;
+
+    enigma.set_message_key(message_key);
+    let plaintext = type_text(&mut enigma, body)?;
+    Ok(Received { message_key, plaintext })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> DailyKey {
+        "B II IV V 02 21 12 AV BS CG DL FU HZ IN KM OW RX".parse().unwrap()
+    }
+
+    #[test]
+    fn test_operation_barbarossa_1941() {
+        let received = receive(
+            Model::EnigmaI,
+            &key(),
+            "WXC KCH",
+            "EDPUD NRGYS ZRCXN UYTPO MRMBO FKTBZ REZKM LXLVE",
+        )
+        .unwrap();
+        assert_eq!(received.message_key, "BLA".parse().unwrap());
+        assert_eq!(received.plaintext, "AUFKLXABTEILUNGXVONXKURTINOWAXKURTINOWAX");
+    }
+
+    #[test]
+    fn test_clear_start_round_trip() {
+        let start = "WXC".parse().unwrap();
+        let sent = send(Model::EnigmaI, &key(), start, "BLA".parse().unwrap(), "AUFKLX").unwrap();
+        assert_eq!(sent.indicator, "WXC KCH");
+        assert_eq!(sent.body, "EDPUDN");
+
+        assert_eq!(
+            receive(Model::EnigmaI, &key(), "WXC", &sent.body),
+            Err(ProcedureError::InvalidIndicator)
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Heer and Luftwaffe procedure until May 1940.
+//!
+//! The operator sets the machine to the Grundstellung of the daily key and
+//! types the three-letter message key twice. The six resulting letters are the
+//! indicator. The rotors are then turned to the message key to encipher the
+//! body.
+
+use super::type_text;
+use super::ProcedureError;
+use super::Received;
+use super::Transmission;
+use crate::core::engine::Enigma;
+use crate::historical_machines::model::Model;
+use crate::key::DailyKey;
+use crate::key::MessageKey;
+
+fn machine_at_grundstellung(model: Model, key: &DailyKey) -> Result<Enigma, ProcedureError> {
+    if key.grundstellung.is_none() {
+        return Err(ProcedureError::MissingGrundstellung);
+    }
+    Ok(Enigma::from_key(model, key)?)
+}
+
+pub fn send(
+    model: Model,
+    key: &DailyKey,
+    message_key: MessageKey,
+    plaintext: &str,
+) -> Result<Transmission, ProcedureError> {
+    let mut enigma = machine_at_grundstellung(model, key)?;
+    let indicator = type_text(&mut enigma, &format!("{message_key}{message_key}"))?;
+
+    enigma.set_message_key(message_key);
+    let body = type_text(&mut enigma, plaintext)?;
+    Ok(Transmission { indicator, body })
+}
+
+pub fn receive(
+    model: Model,
+    key: &DailyKey,
+    indicator: &str,
+    body: &str,
+) -> Result<Received, ProcedureError> {
+    let mut enigma = machine_at_grundstellung(model, key)?;
+    let doubled = type_text(&mut enigma, indicator)?;
+    if doubled.len() != 6 {
+        return Err(ProcedureError::InvalidIndicator);
+    }
+
+    let first: MessageKey = doubled[..3].parse()?;
+    let second: MessageKey = doubled[3..].parse()?;
+    if first != second {
+        return Err(ProcedureError::IndicatorMismatch(first, second));
+    }
+
+    enigma.set_message_key(first);
+    let plaintext = type_text(&mut enigma, body)?;
+    Ok(Received { message_key: first, plaintext })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> DailyKey {
+        "A II I III 24 13 22 AM FI NV PS TU WZ ABL".parse().unwrap()
+    }
+
+    #[test]
+    fn test_doubled_indicator_round_trip() {
+        let message_key = "RTJ".parse().unwrap();
+        let sent = send(Model::EnigmaI, &key(), message_key, "FEINDXBEOBACHTET").unwrap();
+        assert_eq!(sent.indicator.len(), 6);
+        assert_ne!(sent.indicator[..3], sent.indicator[3..]);
+
+        let received = receive(Model::EnigmaI, &key(), &sent.indicator, &sent.body).unwrap();
+        assert_eq!(received.message_key, message_key);
+        assert_eq!(received.plaintext, "FEINDXBEOBACHTET");
+    }
+
+    #[test]
+    fn test_doubled_indicator_garbled() {
+        let message_key = "RTJ".parse().unwrap();
+        let sent = send(Model::EnigmaI, &key(), message_key, "FEIND").unwrap();
+
+        let mut garbled = sent.indicator.into_bytes();
+        garbled[4] = if garbled[4] == b'Z' { b'A' } else { garbled[4] + 1 };
+        let garbled = String::from_utf8(garbled).unwrap();
+        assert!(matches!(
+            receive(Model::EnigmaI, &key(), &garbled, &sent.body),
+            Err(ProcedureError::IndicatorMismatch(..))
+        ));
+
+        let mut no_grundstellung = key();
+        no_grundstellung.grundstellung = None;
+        assert_eq!(
+            send(Model::EnigmaI, &no_grundstellung, message_key, "FEIND"),
+            Err(ProcedureError::MissingGrundstellung)
+        );
+    }
+}
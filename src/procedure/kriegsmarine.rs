@@ -0,0 +1,317 @@
+//! Kriegsmarine indicator procedure.
+//!
+//! The operator picks two trigrams from the Kenngruppenbuch: the
+//! Schlüsselkenngruppe, which tells the receiver which key net is used, and the
+//! Verfahrenkenngruppe. The Verfahrenkenngruppe enciphered at the Grundstellung
+//! of the daily key gives the message key. Both trigrams are written in two
+//! rows with a padding letter each,
+//!
+//! ```text
+//! X Y V T      padding, Schlüsselkenngruppe
+//! D W X P      Verfahrenkenngruppe, padding
+//! ```
+//!
+//! and the four vertical bigrams (`XD`, `YW`, `VX`, `TP`) are replaced using
+//! the secret bigram table (Doppelbuchstabentauschtafel). Written back in two
+//! rows, the substitutes give two four-letter indicator groups which are sent
+//! as the first and again as the last groups of the signal.
+
+use core::fmt;
+use core::str::FromStr;
+
+use super::letters;
+use super::type_text;
+use super::ProcedureError;
+use super::Received;
+use super::Transmission;
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::engine::Enigma;
+use crate::historical_machines::model::Model;
+use crate::key::DailyKey;
+use crate::key::Kenngruppe;
+use crate::key::MessageKey;
+
+type Bigram = [EnigmaAlphabet; 2];
+
+fn index(bigram: Bigram) -> usize {
+    usize::from(bigram[0]) * 26 + usize::from(bigram[1])
+}
+
+fn bigram(index: usize) -> Bigram {
+    [EnigmaAlphabet::new((index / 26) as u8), EnigmaAlphabet::new((index % 26) as u8)]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BigramTableError {
+    /// Entry number `n` (counting from 0) is not of the form `AB=CD`
+    InvalidEntry(usize),
+    /// The bigram is swapped more than once, or with itself
+    Repeated(Bigram),
+    /// The bigram has no substitute
+    Missing(Bigram),
+}
+
+impl fmt::Display for BigramTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BigramTableError::InvalidEntry(n) => write!(f, "entry {n} is not of the form AB=CD"),
+            BigramTableError::Repeated([a, b]) => {
+                write!(f, "{}{} is swapped more than once", a.to_char(), b.to_char())
+            }
+            BigramTableError::Missing([a, b]) => {
+                write!(f, "{}{} has no substitute", a.to_char(), b.to_char())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BigramTableError {}
+
+/// Reciprocal bigram substitution table (Doppelbuchstabentauschtafel)
+///
+/// Every one of the 676 bigrams is swapped with exactly one other bigram. As
+/// text the table is a whitespace separated list of the 338 swaps, e.g.
+/// `AA=QT AB=MX ...`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BigramTable {
+    substitutes: [u16; 676],
+}
+
+impl BigramTable {
+    pub fn from_swaps(
+        swaps: impl IntoIterator<Item = (Bigram, Bigram)>,
+    ) -> Result<Self, BigramTableError> {
+        let mut substitutes = [u16::MAX; 676];
+        for (a, b) in swaps {
+            for (from, to) in [(a, b), (b, a)] {
+                if from == to || substitutes[index(from)] != u16::MAX {
+                    return Err(BigramTableError::Repeated(from));
+                }
+                substitutes[index(from)] = index(to) as u16;
+            }
+        }
+        match substitutes.iter().position(|&substitute| substitute == u16::MAX) {
+            Some(missing) => Err(BigramTableError::Missing(bigram(missing))),
+            None => Ok(Self { substitutes }),
+        }
+    }
+
+    pub fn substitute(&self, bigram: Bigram) -> Bigram {
+        self::bigram(usize::from(self.substitutes[index(bigram)]))
+    }
+
+    /// The 338 swaps, each with the alphabetically lower bigram first
+    pub fn swaps(&self) -> impl Iterator<Item = (Bigram, Bigram)> + '_ {
+        (0..676)
+            .filter(|&i| i < usize::from(self.substitutes[i]))
+            .map(|i| (bigram(i), bigram(usize::from(self.substitutes[i]))))
+    }
+}
+
+impl fmt::Debug for BigramTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BigramTable").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for BigramTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, ([a, b], [c, d])) in self.swaps().enumerate() {
+            let separator = match i {
+                0 => "",
+                _ if i % 13 == 0 => "\n",
+                _ => " ",
+            };
+            let [a, b, c, d] = [a, b, c, d].map(char::from);
+            write!(f, "{separator}{a}{b}={c}{d}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BigramTable {
+    type Err = BigramTableError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let swaps = s
+            .split_whitespace()
+            .enumerate()
+            .map(|(n, entry)| match entry.as_bytes() {
+                &[a, b, b'=', c, d] if [a, b, c, d].iter().all(u8::is_ascii_uppercase) => {
+                    let [a, b, c, d] = [a, b, c, d].map(EnigmaAlphabet::from_ascii);
+                    Ok(([a, b], [c, d]))
+                }
+                _ => Err(BigramTableError::InvalidEntry(n)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_swaps(swaps)
+    }
+}
+
+/// The two trigrams picked from the Kenngruppenbuch for one message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Indicator {
+    pub schluessel_kenngruppe: Kenngruppe,
+    pub verfahren_kenngruppe: Kenngruppe,
+}
+
+impl Indicator {
+    /// Disguise the indicator as two four-letter groups, using `padding` as the
+    /// filler letters of the first and second row
+    pub fn encode(&self, table: &BigramTable, padding: [EnigmaAlphabet; 2]) -> [String; 2] {
+        let [k1, k2, k3] = self.schluessel_kenngruppe.0;
+        let [v1, v2, v3] = self.verfahren_kenngruppe.0;
+        let columns = [[padding[0], v1], [k1, v2], [k2, v3], [k3, padding[1]]];
+        let substitutes = columns.map(|column| table.substitute(column));
+        [0, 1].map(|row| substitutes.iter().map(|bigram| bigram[row].to_char()).collect())
+    }
+
+    /// Recover the indicator from the two four-letter groups
+    pub fn decode(table: &BigramTable, groups: [&str; 2]) -> Result<Self, ProcedureError> {
+        let mut rows = [[EnigmaAlphabet::new(0); 4]; 2];
+        for (row, group) in rows.iter_mut().zip(groups) {
+            let letters = letters(group).collect::<Result<Vec<_>, _>>()?;
+            *row = letters.try_into().map_err(|_| ProcedureError::InvalidIndicator)?;
+        }
+        let columns: [Bigram; 4] =
+            core::array::from_fn(|column| table.substitute([rows[0][column], rows[1][column]]));
+        let [[_, v1], [k1, v2], [k2, v3], [k3, _]] = columns;
+        Ok(Self {
+            schluessel_kenngruppe: Kenngruppe([k1, k2, k3]),
+            verfahren_kenngruppe: Kenngruppe([v1, v2, v3]),
+        })
+    }
+}
+
+fn message_key(
+    enigma: &mut Enigma,
+    key: &DailyKey,
+    indicator: &Indicator,
+) -> Result<MessageKey, ProcedureError> {
+    let grundstellung = key.grundstellung.ok_or(ProcedureError::MissingGrundstellung)?;
+    enigma.set_positions(grundstellung.0);
+    Ok(type_text(enigma, &indicator.verfahren_kenngruppe.to_string())?.parse()?)
+}
+
+/// Encipher `plaintext`. The indicator of the transmission holds the two
+/// indicator groups, see [`signal`] for the full layout.
+pub fn send(
+    model: Model,
+    key: &DailyKey,
+    table: &BigramTable,
+    indicator: &Indicator,
+    padding: [EnigmaAlphabet; 2],
+    plaintext: &str,
+) -> Result<Transmission, ProcedureError> {
+    let mut enigma = Enigma::from_key(model, key)?;
+    let message_key = message_key(&mut enigma, key, indicator)?;
+
+    enigma.set_message_key(message_key);
+    let body = type_text(&mut enigma, plaintext)?;
+    Ok(Transmission { indicator: indicator.encode(table, padding).join(" "), body })
+}
+
+/// Lay out a transmission as sent: four-letter groups, the indicator groups
+/// first and repeated at the end
+pub fn signal(transmission: &Transmission) -> String {
+    let body =
+        transmission.body.as_bytes().chunks(4).map(|group| std::str::from_utf8(group).unwrap());
+    let mut groups = vec![transmission.indicator.as_str()];
+    groups.extend(body);
+    groups.push(&transmission.indicator);
+    groups.join(" ")
+}
+
+/// Decipher a signal laid out by [`signal`], returning the indicator next to
+/// the usual message key and plaintext
+pub fn receive(
+    model: Model,
+    key: &DailyKey,
+    table: &BigramTable,
+    signal: &str,
+) -> Result<(Indicator, Received), ProcedureError> {
+    let letters: String =
+        letters(signal).map(|letter| letter.map(char::from)).collect::<Result<_, _>>()?;
+    if letters.len() < 16 || letters[..8] != letters[letters.len() - 8..] {
+        return Err(ProcedureError::InvalidIndicator);
+    }
+    let indicator = Indicator::decode(table, [&letters[..4], &letters[4..8]])?;
+
+    let mut enigma = Enigma::from_key(model, key)?;
+    let message_key = message_key(&mut enigma, key, &indicator)?;
+
+    enigma.set_message_key(message_key);
+    let plaintext = type_text(&mut enigma, &letters[8..letters.len() - 8])?;
+    Ok((indicator, Received { message_key, plaintext }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Swaps every bigram with a scrambled partner
+    fn table() -> BigramTable {
+        let mut order: Vec<usize> = (0..676).collect();
+        order.sort_by_key(|&i| (i * 263) % 677);
+        BigramTable::from_swaps(order.chunks(2).map(|pair| (bigram(pair[0]), bigram(pair[1]))))
+            .unwrap()
+    }
+
+    fn key() -> DailyKey {
+        "B VI II VIII 05 14 22 AT BL DF GJ HM NW OP QY RZ VX UKS".parse().unwrap()
+    }
+
+    fn indicator() -> Indicator {
+        Indicator {
+            schluessel_kenngruppe: "YVT".parse().unwrap(),
+            verfahren_kenngruppe: "DWX".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_bigram_table_text() {
+        let table = table();
+        assert_eq!(table.swaps().count(), 338);
+        assert_eq!(table.to_string().parse::<BigramTable>().unwrap(), table);
+
+        let ab = ['A'.into(), 'B'.into()];
+        assert_eq!(table.substitute(table.substitute(ab)), ab);
+
+        assert_eq!("AB=CD AB=EF".parse::<BigramTable>(), Err(BigramTableError::Repeated(ab)));
+        assert_eq!("AB=CD XY".parse::<BigramTable>(), Err(BigramTableError::InvalidEntry(1)));
+        assert_eq!(
+            "AB=CD".parse::<BigramTable>(),
+            Err(BigramTableError::Missing(['A'.into(), 'A'.into()]))
+        );
+    }
+
+    #[test]
+    fn test_indicator_groups() {
+        let groups = indicator().encode(&table(), ['X'.into(), 'P'.into()]);
+        assert_eq!(Indicator::decode(&table(), [&groups[0], &groups[1]]), Ok(indicator()));
+    }
+
+    #[test]
+    fn test_kriegsmarine_round_trip() {
+        let plaintext = "VONXUBOOTXUNDXNULLXVIERXSIEBENXKURSXNORD";
+        let sent =
+            send(Model::M3, &key(), &table(), &indicator(), ['X'.into(), 'P'.into()], plaintext)
+                .unwrap();
+        let signal = signal(&sent);
+        assert_eq!(signal.split(' ').count(), 14);
+        assert!(signal.ends_with(&sent.indicator));
+
+        let (indicator, received) = receive(Model::M3, &key(), &table(), &signal).unwrap();
+        assert_eq!(indicator, self::indicator());
+        assert_eq!(received.plaintext, plaintext);
+
+        let mut enigma = Enigma::from_key(Model::M3, &key()).unwrap();
+        assert_eq!(type_text(&mut enigma, "DWX").unwrap(), received.message_key.to_string());
+
+        let truncated = &signal[..signal.len() - 5];
+        assert_eq!(
+            receive(Model::M3, &key(), &table(), truncated),
+            Err(ProcedureError::InvalidIndicator)
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! # Message procedures
+//!
+//! How operators turned a daily key and a message key into what went over the
+//! air, and back.
+//!
+//! - [`doubled_indicator`]: Heer and Luftwaffe before May 1940, the message
+//!   key typed twice at the daily Grundstellung.
+//! - [`clear_start`]: Heer and Luftwaffe from May 1940, the operator picks the
+//!   start for enciphering the message key and sends it in the clear.
+//! - [`kriegsmarine`]: the Navy, message key taken from the Kenngruppenbuch and
+//!   disguised with bigram tables.
+
+use core::fmt;
+
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::core::engine::Enigma;
+use crate::key::KeyError;
+use crate::key::MessageKey;
+
+pub mod clear_start;
+pub mod doubled_indicator;
+pub mod kriegsmarine;
+
+/// What goes over the air
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transmission {
+    pub indicator: String,
+    pub body: String,
+}
+
+/// What the receiving operator gets out of a [`Transmission`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Received {
+    pub message_key: MessageKey,
+    pub plaintext: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcedureError {
+    Key(KeyError),
+    /// The procedure needs a Grundstellung but the daily key has none
+    MissingGrundstellung,
+    /// The indicator does not have the layout the procedure expects
+    InvalidIndicator,
+    /// The two deciphered copies of a doubled message key differ, which
+    /// usually means the indicator was garbled in transmission
+    IndicatorMismatch(MessageKey, MessageKey),
+    /// Text contains something that cannot be typed on the machine
+    InvalidLetter(char),
+    InvalidBigramTable(kriegsmarine::BigramTableError),
+}
+
+impl fmt::Display for ProcedureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcedureError::Key(e) => write!(f, "invalid key: {e}"),
+            ProcedureError::MissingGrundstellung => write!(f, "daily key has no Grundstellung"),
+            ProcedureError::InvalidIndicator => write!(f, "invalid indicator"),
+            ProcedureError::IndicatorMismatch(first, second) => {
+                write!(f, "indicator deciphers to {first} and {second}")
+            }
+            ProcedureError::InvalidLetter(ch) => write!(f, "{ch:?} cannot be typed"),
+            ProcedureError::InvalidBigramTable(e) => write!(f, "invalid bigram table: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ProcedureError {}
+
+impl From<KeyError> for ProcedureError {
+    fn from(value: KeyError) -> Self {
+        ProcedureError::Key(value)
+    }
+}
+
+impl From<kriegsmarine::BigramTableError> for ProcedureError {
+    fn from(value: kriegsmarine::BigramTableError) -> Self {
+        ProcedureError::InvalidBigramTable(value)
+    }
+}
+
+/// Letters of `text`, skipping whitespace
+fn letters(text: &str) -> impl Iterator<Item = Result<EnigmaAlphabet, ProcedureError>> + '_ {
+    text.chars().filter(|ch| !ch.is_whitespace()).map(|ch| match ch {
+        'A'..='Z' => Ok(ch.into()),
+        _ => Err(ProcedureError::InvalidLetter(ch)),
+    })
+}
+
+/// Type `text` on `enigma`, ignoring whitespace
+fn type_text(enigma: &mut Enigma, text: &str) -> Result<String, ProcedureError> {
+    let letters = letters(text).collect::<Result<Vec<_>, _>>()?;
+    Ok(letters.into_iter().map(|letter| enigma.encode(letter).to_char()).collect())
+}
@@ -0,0 +1,280 @@
+//! Small seedable pseudo random number generator (SplitMix64).
+//!
+//! Not suitable for anything but exercises and simulations: the same seed
+//! always gives the same sequence, on every platform.
+//!
+//! Besides plain numbers it picks complete daily keys, see
+//! [`Rng::daily_key`], and message keys the way an operator would, see
+//! [`Rng::message_key`].
+
+use crate::core::alphabet::EnigmaAlphabet;
+use crate::historical_machines::model::Model;
+use crate::historical_machines::model::ReflectorId;
+use crate::historical_machines::model::RotorId;
+use crate::key::DailyKey;
+use crate::key::Grundstellung;
+use crate::key::KeyError;
+use crate::key::MessageKey;
+use crate::key::Ringstellung;
+use crate::key::Steckerverbindungen;
+use crate::key::Walzenlage;
+
+/// Rows of the Enigma keyboard
+const KEYBOARD: [&[u8]; 3] = [b"QWERTZUIO", b"ASDFGHJK", b"PYXCVBNML"];
+
+/// Restrictions on [`Rng::daily_key`], anything left `None` is picked at random
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyConstraints {
+    pub reflector: Option<ReflectorId>,
+    pub rotors: Option<[RotorId; 3]>,
+    /// Exact number of plug cables, 0 to 13
+    pub cables: Option<usize>,
+    /// No plug joins neighbours in the alphabet, like `AB` or `ST`
+    pub no_adjacent_plugs: bool,
+}
+
+/// How an operator picks message keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OperatorHabit {
+    /// Three independent random letters, as the rules demanded
+    #[default]
+    Careful,
+    /// The same letter three times, `AAA`
+    RepeatedLetter,
+    /// Three letters in alphabetical order, `ABC`
+    AlphabetRun,
+    /// Three neighbouring keys on the keyboard, `QWE`
+    KeyboardRun,
+    /// One of the bad habits above in the given percentage of keys, careful
+    /// keys otherwise
+    Sloppy(u8),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform number in `0.0..1.0`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform number in `0..n`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "empty range");
+        let n = n as u64;
+        // reject the top values that would bias the remainder
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return (x % n) as usize;
+            }
+        }
+    }
+
+    pub fn letter(&mut self) -> EnigmaAlphabet {
+        EnigmaAlphabet::new(self.below(26) as u8)
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+
+    /// Complete daily key for `model`: wheel order, rings, plugs and a
+    /// Grundstellung for the rotor positions
+    pub fn daily_key(
+        &mut self,
+        model: Model,
+        constraints: &KeyConstraints,
+    ) -> Result<DailyKey, KeyError> {
+        let reflector = match constraints.reflector {
+            Some(reflector) => reflector,
+            None => *self.choose(model.reflectors()),
+        };
+        let rotors = match constraints.rotors {
+            Some(rotors) => rotors,
+            None => {
+                let mut rotors = [RotorId::I; 8];
+                let rotors = &mut rotors[..model.rotors().len()];
+                rotors.copy_from_slice(model.rotors());
+                self.shuffle(rotors);
+                [rotors[0], rotors[1], rotors[2]]
+            }
+        };
+        let walzenlage = Walzenlage { reflector, rotors };
+        walzenlage.check(model)?;
+
+        let cables = match constraints.cables {
+            Some(cables @ 14..) => return Err(KeyError::TooManyCables(cables)),
+            Some(cables) => cables,
+            None => self.below(14),
+        };
+
+        Ok(DailyKey {
+            walzenlage,
+            ringstellung: Ringstellung([0; 3].map(|_| self.below(26) as u8)),
+            steckerverbindungen: self.steckerverbindungen(cables, constraints.no_adjacent_plugs),
+            grundstellung: Some(Grundstellung(self.letters())),
+        })
+    }
+
+    /// `cables` plug pairs of distinct letters, none joining neighbours in
+    /// the alphabet if `no_adjacent`
+    pub(crate) fn steckerverbindungen(
+        &mut self,
+        cables: usize,
+        no_adjacent: bool,
+    ) -> Steckerverbindungen {
+        let mut letters: [EnigmaAlphabet; 26] =
+            core::array::from_fn(|i| EnigmaAlphabet::new(i as u8));
+        loop {
+            self.shuffle(&mut letters);
+            let pairs = letters.chunks(2).take(cables).map(|pair| (pair[0], pair[1]));
+            if !no_adjacent || pairs.clone().all(|(a, b)| a.to_u8().abs_diff(b.to_u8()) != 1) {
+                return Steckerverbindungen::from_pairs(pairs).expect("letters are distinct");
+            }
+        }
+    }
+
+    /// Message key as picked by an operator with `habit`
+    pub fn message_key(&mut self, habit: OperatorHabit) -> MessageKey {
+        let letters = match habit {
+            OperatorHabit::Careful => self.letters(),
+            OperatorHabit::RepeatedLetter => [self.letter(); 3],
+            OperatorHabit::AlphabetRun => {
+                let first = self.letter();
+                [0, 1, 2].map(|i| first + i)
+            }
+            OperatorHabit::KeyboardRun => {
+                let row = *self.choose(&KEYBOARD);
+                let start = self.below(row.len() - 2);
+                [0, 1, 2].map(|i| EnigmaAlphabet::from_ascii(row[start + i]))
+            }
+            OperatorHabit::Sloppy(percent) => {
+                let habit = if self.below(100) < usize::from(percent) {
+                    *self.choose(&[
+                        OperatorHabit::RepeatedLetter,
+                        OperatorHabit::AlphabetRun,
+                        OperatorHabit::KeyboardRun,
+                    ])
+                } else {
+                    OperatorHabit::Careful
+                };
+                return self.message_key(habit);
+            }
+        };
+        MessageKey(letters)
+    }
+
+    pub(crate) fn letters(&mut self) -> [EnigmaAlphabet; 3] {
+        [0; 3].map(|_| self.letter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_reproducible() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+
+        let mut counts = [0; 26];
+        for _ in 0..26_000 {
+            counts[usize::from(a.letter())] += 1;
+        }
+        assert!(counts.iter().all(|&count| (800..1200).contains(&count)));
+        assert!((0..1000).map(|_| a.next_f64()).all(|x| (0.0..1.0).contains(&x)));
+    }
+
+    #[test]
+    fn test_daily_key() {
+        for model in [Model::EnigmaI, Model::M3] {
+            for seed in 0..50 {
+                let key = Rng::new(seed).daily_key(model, &KeyConstraints::default()).unwrap();
+                assert_eq!(Rng::new(seed).daily_key(model, &KeyConstraints::default()), Ok(key));
+                key.walzenlage.check(model).unwrap();
+                assert!(key.steckerverbindungen.len() <= 13);
+                assert!(key.grundstellung.is_some());
+            }
+        }
+
+        let constraints = KeyConstraints {
+            reflector: Some(ReflectorId::C),
+            cables: Some(10),
+            no_adjacent_plugs: true,
+            ..Default::default()
+        };
+        let mut rng = Rng::new(7);
+        for _ in 0..50 {
+            let key = rng.daily_key(Model::M3, &constraints).unwrap();
+            assert_eq!(key.walzenlage.reflector, ReflectorId::C);
+            assert_eq!(key.steckerverbindungen.len(), 10);
+            assert!(key.steckerverbindungen.pairs().all(|(a, b)| b.to_u8() - a.to_u8() != 1));
+        }
+
+        let rotors = Some([RotorId::VI, RotorId::I, RotorId::II]);
+        let constraints = KeyConstraints { rotors, ..Default::default() };
+        assert_eq!(
+            rng.daily_key(Model::EnigmaI, &constraints),
+            Err(KeyError::RotorNotInModel(RotorId::VI))
+        );
+        let constraints = KeyConstraints { reflector: Some(ReflectorId::A), ..Default::default() };
+        assert_eq!(
+            rng.daily_key(Model::M3, &constraints),
+            Err(KeyError::ReflectorNotInModel(ReflectorId::A))
+        );
+        let constraints = KeyConstraints { cables: Some(14), ..Default::default() };
+        assert_eq!(rng.daily_key(Model::M3, &constraints), Err(KeyError::TooManyCables(14)));
+    }
+
+    #[test]
+    fn test_message_key_habits() {
+        let mut rng = Rng::new(3);
+        for _ in 0..100 {
+            let [a, b, c] = rng.message_key(OperatorHabit::RepeatedLetter).0;
+            assert!(a == b && b == c);
+
+            let [a, b, c] = rng.message_key(OperatorHabit::AlphabetRun).0;
+            assert!(b == a + 1 && c == b + 1);
+
+            let key =
+                rng.message_key(OperatorHabit::KeyboardRun).0.map(|letter| letter.to_char() as u8);
+            assert!(KEYBOARD.iter().any(|row| row.windows(3).any(|keys| keys == key)));
+        }
+
+        let sloppy = (0..1000)
+            .map(|_| rng.message_key(OperatorHabit::Sloppy(30)).0)
+            .filter(|[a, b, c]| a == b && b == c || *b == *a + 1 && *c == *b + 1)
+            .count();
+        assert!((150..300).contains(&sloppy), "{sloppy}");
+    }
+}